@@ -0,0 +1,926 @@
+use rayon::slice::ParallelSliceMut;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortMode {
+    String,
+    Numeric,
+    Month,
+    Suffix,
+    GeneralNumeric,
+    Version,
+    Radix(u32),
+    Length(LengthUnit),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LengthUnit {
+    Bytes,
+    Chars,
+}
+
+impl SortMode {
+    pub fn is_numeric(self) -> bool {
+        matches!(self, SortMode::Numeric | SortMode::Suffix | SortMode::GeneralNumeric | SortMode::Radix(_))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EmptyKeys {
+    Compare,
+    First,
+    Last,
+}
+
+#[derive(Clone)]
+pub struct KeySpec {
+    pub start: isize,
+    pub start_char: usize,
+    pub end: Option<isize>,
+    pub end_char: usize,
+    pub thousands_sep: Option<char>,
+    pub currency: Option<String>,
+    pub width: Option<usize>,
+    pub empty_keys: EmptyKeys,
+    pub mode: SortMode,
+    pub reverse: bool,
+    pub ignore_case: bool,
+    pub skip_blanks: bool,
+    pub ignore_nonprinting: bool,
+    pub dictionary_order: bool,
+    pub collate: bool,
+}
+
+impl Default for KeySpec {
+    fn default() -> Self {
+        KeySpec {
+            start: 1,
+            start_char: 1,
+            end: None,
+            end_char: 0,
+            thousands_sep: None,
+            currency: None,
+            width: None,
+            empty_keys: EmptyKeys::Compare,
+            mode: SortMode::String,
+            reverse: false,
+            ignore_case: false,
+            skip_blanks: false,
+            ignore_nonprinting: false,
+            dictionary_order: false,
+            collate: false,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SortOptions {
+    pub global: KeySpec,
+    pub keys: Vec<KeySpec>,
+    pub separator: Option<String>,
+    pub reverse: bool,
+    pub stable: bool,
+    pub unique: bool,
+    pub parallel: bool,
+    pub strip_cr: bool,
+    pub json_field: Option<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum SortError {
+    Io(io::Error),
+    InvalidKey(String),
+    InvalidOption(String),
+    Parse(String),
+    Disorder(String),
+}
+
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SortError::Io(err) => write!(f, "{}", describe_io_error(err)),
+            SortError::InvalidKey(message)
+            | SortError::InvalidOption(message)
+            | SortError::Parse(message)
+            | SortError::Disorder(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for SortError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SortError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SortError {
+    fn from(err: io::Error) -> Self {
+        err.downcast::<SortError>().unwrap_or_else(SortError::Io)
+    }
+}
+
+pub fn describe_io_error(err: &io::Error) -> String {
+    let message = err.to_string();
+    match message.find(" (os error") {
+        Some(pos) => message[..pos].to_string(),
+        None => message,
+    }
+}
+
+fn month_index(value: &str) -> usize {
+    let value = value.trim_start();
+    let token = &value[..value.find(|c: char| !c.is_alphabetic()).unwrap_or(value.len())];
+    if token.len() < 3 {
+        return 0;
+    }
+    MONTHS
+        .iter()
+        .position(|month| month.get(..token.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(token)))
+        .map_or(0, |index| index + 1)
+}
+
+fn suffix_multiplier(unit: &str) -> Option<f64> {
+    match unit {
+        "K"|"k" => Some(1e3),
+        "M"|"m" => Some(1e6),
+        "G"|"g" => Some(1e9),
+        "T"|"t" => Some(1e12),
+        "P"|"p" => Some(1e15),
+        "Ki" => Some(1024.0),
+        "Mi" => Some(1024.0 * 1024.0),
+        "Gi" => Some(1024.0 * 1024.0 * 1024.0),
+        "Ti" => Some(1024.0 * 1024.0 * 1024.0 * 1024.0),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy)]
+enum NumericKey {
+    Invalid,
+    Number(f64),
+}
+
+impl PartialEq for NumericKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for NumericKey {}
+
+impl PartialOrd for NumericKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NumericKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (NumericKey::Number(a), NumericKey::Number(b)) => a.total_cmp(b),
+            (NumericKey::Invalid, NumericKey::Number(_)) => Ordering::Less,
+            (NumericKey::Number(_), NumericKey::Invalid) => Ordering::Greater,
+            (NumericKey::Invalid, NumericKey::Invalid) => Ordering::Equal,
+        }
+    }
+}
+
+impl From<Option<f64>> for NumericKey {
+    fn from(value: Option<f64>) -> Self {
+        value.map_or(NumericKey::Invalid, NumericKey::Number)
+    }
+}
+
+fn numeric_prefix(s: &str) -> (&str, &str) {
+    let sign = usize::from(s.starts_with(['-', '+']));
+    let digits_from = |from: usize| s[from..].find(|c: char| !c.is_ascii_digit()).map_or(s.len(), |pos| from + pos);
+    let integer = digits_from(sign);
+    let end = if s[integer..].starts_with('.') { digits_from(integer + 1) } else { integer };
+    if !s[sign..end].contains(|c: char| c.is_ascii_digit()) {
+        return ("", s);
+    }
+    s.split_at(end)
+}
+
+fn exponent_len(s: &str) -> usize {
+    let Some(exponent) = s.strip_prefix(['e', 'E']) else {
+        return 0;
+    };
+    let sign = usize::from(exponent.starts_with(['-', '+']));
+    match exponent[sign..].find(|c: char| !c.is_ascii_digit()).unwrap_or(exponent.len() - sign) {
+        0 => 0,
+        digits => 1 + sign + digits,
+    }
+}
+
+fn numeric_key(s: &str) -> NumericKey {
+    numeric_prefix(s).0.parse::<f64>().ok().into()
+}
+
+fn parse_with_suffix(s: &str) -> NumericKey {
+    let (number, rest) = numeric_prefix(s);
+    let unit = rest.strip_prefix(' ').unwrap_or(rest);
+    let multiplier = unit
+        .get(..2)
+        .and_then(suffix_multiplier)
+        .or_else(|| unit.get(..1).and_then(suffix_multiplier))
+        .unwrap_or(1.0);
+    number.parse::<f64>().ok().map(|number| number * multiplier).into()
+}
+
+fn parse_general_numeric(s: &str) -> NumericKey {
+    let (number, rest) = numeric_prefix(s);
+    if !number.is_empty() {
+        return s[..number.len() + exponent_len(rest)].parse::<f64>().ok().into();
+    }
+    let sign = usize::from(s.starts_with(['-', '+']));
+    ["infinity", "inf", "nan"]
+        .iter()
+        .find(|word| s[sign..].get(..word.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(word)))
+        .and_then(|word| s[..sign + word.len()].parse::<f64>().ok())
+        .into()
+}
+
+fn parse_radix(s: &str, radix: u32) -> NumericKey {
+    if !(2..=36).contains(&radix) {
+        return NumericKey::Invalid;
+    }
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let digits = match radix {
+        16 => digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")).unwrap_or(digits),
+        _ => digits,
+    };
+    let digits = &digits[..digits.find(|c: char| !c.is_digit(radix)).unwrap_or(digits.len())];
+    u128::from_str_radix(digits, radix)
+        .ok()
+        .map(|value| if negative { -(value as f64) } else { value as f64 })
+        .into()
+}
+
+fn compare_month(a: &str, b: &str) -> Ordering {
+    month_index(a).cmp(&month_index(b))
+}
+
+fn numeric_value(value: &str, mode: SortMode) -> NumericKey {
+    let value = value.trim_start();
+    match mode {
+        SortMode::Suffix => parse_with_suffix(value),
+        SortMode::GeneralNumeric => parse_general_numeric(value),
+        SortMode::Radix(radix) => parse_radix(value, radix),
+        _ => numeric_key(value),
+    }
+}
+
+fn split_version_run(s: &str) -> (&str, &str) {
+    let is_digit = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s.find(|c: char| c.is_ascii_digit() != is_digit).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn compare_version(a: &str, b: &str) -> Ordering {
+    let (mut rest_a, mut rest_b) = (a, b);
+    while !rest_a.is_empty() && !rest_b.is_empty() {
+        let (run_a, tail_a) = split_version_run(rest_a);
+        let (run_b, tail_b) = split_version_run(rest_b);
+        let digits_a = run_a.starts_with(|c: char| c.is_ascii_digit());
+        let digits_b = run_b.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if digits_a && digits_b {
+            let value_a = run_a.trim_start_matches('0');
+            let value_b = run_b.trim_start_matches('0');
+            value_a.len().cmp(&value_b.len()).then_with(|| value_a.cmp(value_b))
+        } else {
+            run_a.cmp(run_b)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        rest_a = tail_a;
+        rest_b = tail_b;
+    }
+    rest_a.len().cmp(&rest_b.len()).then_with(|| a.cmp(b))
+}
+
+#[cfg(feature = "collation")]
+fn collate(a: &str, b: &str) -> Ordering {
+    thread_local! {
+        static COLLATOR: std::cell::RefCell<feruca::Collator> = std::cell::RefCell::new(feruca::Collator::default());
+    }
+    COLLATOR.with(|collator| collator.borrow_mut().collate(a, b))
+}
+
+#[cfg(not(feature = "collation"))]
+fn collate(a: &str, b: &str) -> Ordering {
+    a.cmp(b)
+}
+
+fn compare_string(a: &str, b: &str, key: &KeySpec) -> Ordering {
+    match (key.ignore_case, key.collate) {
+        (true, true) => collate(&a.to_lowercase(), &b.to_lowercase()),
+        (true, false) => a.chars().flat_map(char::to_lowercase).cmp(b.chars().flat_map(char::to_lowercase)),
+        (false, true) => collate(a, b),
+        (false, false) => a.cmp(b),
+    }
+}
+
+pub fn compare_values(a: &str, b: &str, key: &KeySpec) -> Ordering {
+    match key.mode {
+        SortMode::String => compare_string(a, b, key),
+        SortMode::Numeric | SortMode::Suffix | SortMode::GeneralNumeric | SortMode::Radix(_) => {
+            numeric_value(a, key.mode).cmp(&numeric_value(b, key.mode))
+        }
+        SortMode::Month => compare_month(a, b),
+        SortMode::Version => compare_version(a, b),
+        SortMode::Length(LengthUnit::Bytes) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+        SortMode::Length(LengthUnit::Chars) => a.chars().count().cmp(&b.chars().count()).then_with(|| a.cmp(b)),
+    }
+}
+
+fn transform_key<'a>(value: &'a str, key: &KeySpec) -> Cow<'a, str> {
+    let value = if key.skip_blanks { value.trim_start() } else { value };
+    let value = match key.currency.as_deref().filter(|_| key.mode.is_numeric()) {
+        Some(symbols) => value.trim_start().trim_start_matches(|c| symbols.contains(c)),
+        None => value,
+    };
+    let grouping = if key.mode.is_numeric() { key.thousands_sep } else { None };
+    if !grouping.is_some_and(|c| value.contains(c)) && !key.ignore_nonprinting && !key.dictionary_order {
+        let end = key.width.and_then(|width| value.char_indices().nth(width)).map_or(value.len(), |(i, _)| i);
+        return Cow::Borrowed(&value[..end]);
+    }
+    let value = value
+        .chars()
+        .filter(|&c| Some(c) != grouping)
+        .filter(|&c| !key.ignore_nonprinting || !c.is_control())
+        .filter(|&c| !key.dictionary_order || c.is_ascii_alphanumeric() || c == ' ' || c == '\t')
+        .take(key.width.unwrap_or(usize::MAX))
+        .collect();
+    Cow::Owned(value)
+}
+
+fn compare_key(a: &str, b: &str, key: &KeySpec, global_reverse: bool) -> Ordering {
+    let a = transform_key(a, key);
+    let b = transform_key(b, key);
+    let placement = match (key.empty_keys, a.is_empty(), b.is_empty()) {
+        (EmptyKeys::First, true, false) | (EmptyKeys::Last, false, true) => Some(Ordering::Less),
+        (EmptyKeys::First, false, true) | (EmptyKeys::Last, true, false) => Some(Ordering::Greater),
+        _ => None,
+    };
+    if let Some(ordering) = placement {
+        return if global_reverse { ordering.reverse() } else { ordering };
+    }
+    let ordering = compare_values(&a, &b, key);
+    if key.reverse { ordering.reverse() } else { ordering }
+}
+
+#[cfg(feature = "json")]
+fn json_value<'a>(value: &'a serde_json::Value, path: &[String]) -> Option<&'a serde_json::Value> {
+    path.iter().try_fold(value, |value, part| match value {
+        serde_json::Value::Array(items) => part.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => value.get(part),
+    })
+}
+
+#[cfg(feature = "json")]
+pub fn valid_json(line: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(line).is_ok()
+}
+
+#[cfg(not(feature = "json"))]
+pub fn valid_json(_line: &str) -> bool {
+    false
+}
+
+#[cfg(feature = "json")]
+fn json_key(line: &str, path: &[String]) -> String {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+        return String::new();
+    };
+    match json_value(&value, path) {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(serde_json::Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+#[cfg(not(feature = "json"))]
+fn json_key(_line: &str, _path: &[String]) -> String {
+    String::new()
+}
+
+fn without_cr<'a>(line: &'a str, opts: &SortOptions) -> &'a str {
+    if opts.strip_cr { line.strip_suffix('\r').unwrap_or(line) } else { line }
+}
+
+fn key_source<'a>(line: &'a str, opts: &SortOptions) -> Cow<'a, str> {
+    let line = without_cr(line, opts);
+    match &opts.json_field {
+        Some(path) => Cow::Owned(json_key(line, path)),
+        None => Cow::Borrowed(line),
+    }
+}
+
+pub fn key_text(line: &str, opts: &SortOptions) -> String {
+    let line = key_source(line, opts);
+    let normalize = |value: &str, key: &KeySpec| {
+        let value = transform_key(value, key);
+        if key.ignore_case { value.to_lowercase() } else { value.into_owned() }
+    };
+    if opts.keys.is_empty() {
+        return normalize(&line, &opts.global);
+    }
+    opts.keys
+        .iter()
+        .map(|key| normalize(get_column_value(&line, key, opts.separator.as_deref()), key))
+        .collect::<Vec<_>>()
+        .join("\0")
+}
+
+pub fn compare_lines(a: &str, b: &str, opts: &SortOptions) -> Ordering {
+    let (a, b) = (key_source(a, opts), key_source(b, opts));
+    let (a, b) = (a.as_ref(), b.as_ref());
+    if opts.keys.is_empty() {
+        return compare_key(a, b, &opts.global, opts.reverse);
+    }
+    for key in &opts.keys {
+        let value_a = get_column_value(a, key, opts.separator.as_deref());
+        let value_b = get_column_value(b, key, opts.separator.as_deref());
+        let ordering = compare_key(value_a, value_b, key, opts.reverse);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+fn last_resort(a: &str, b: &str, opts: &SortOptions) -> Ordering {
+    if opts.stable || opts.unique {
+        return Ordering::Equal;
+    }
+    compare_string(a, b, &opts.global).then_with(|| a.cmp(b))
+}
+
+pub fn compare_ordered(a: &str, b: &str, opts: &SortOptions) -> Ordering {
+    let ordering = compare_lines(a, b, opts).then_with(|| last_resort(a, b, opts));
+    if opts.reverse { ordering.reverse() } else { ordering }
+}
+
+fn sort_slice<T: Send, F: Fn(&T, &T) -> Ordering + Sync>(items: &mut [T], opts: &SortOptions, compare: F) {
+    match (opts.parallel, opts.stable || opts.unique) {
+        (true, true) => items.par_sort_by(compare),
+        (true, false) => items.par_sort_unstable_by(compare),
+        (false, true) => items.sort_by(compare),
+        (false, false) => items.sort_unstable_by(compare),
+    }
+}
+
+fn cache_key(opts: &SortOptions) -> Option<&KeySpec> {
+    let key = match opts.keys.as_slice() {
+        [] => &opts.global,
+        [key] => key,
+        _ => return None,
+    };
+    (key.empty_keys == EmptyKeys::Compare).then_some(key)
+}
+
+fn cached_value<T>(line: &str, key: &KeySpec, opts: &SortOptions, convert: impl FnOnce(&str) -> T) -> T {
+    let line = key_source(line, opts);
+    let line = line.as_ref();
+    let value = if opts.keys.is_empty() {
+        transform_key(line, key)
+    } else {
+        transform_key(get_column_value(line, key, opts.separator.as_deref()), key)
+    };
+    convert(&value)
+}
+
+fn cached_numeric_value(line: &str, key: &KeySpec, opts: &SortOptions) -> NumericKey {
+    cached_value(line, key, opts, |value| numeric_value(value, key.mode))
+}
+
+pub fn key_number(line: &str, opts: &SortOptions) -> Option<f64> {
+    let key = opts.keys.first().unwrap_or(&opts.global);
+    match cached_numeric_value(line, key, opts) {
+        NumericKey::Number(value) => Some(value),
+        NumericKey::Invalid => None,
+    }
+}
+
+fn sort_by_cached_key<K: Send>(
+    lines: &mut [String],
+    opts: &SortOptions,
+    cache: impl Fn(&str) -> K,
+    compare: impl Fn(&K, &K) -> Ordering + Sync,
+) {
+    let mut decorated: Vec<(K, String)> = lines.iter_mut().map(|line| (cache(line), std::mem::take(line))).collect();
+    sort_slice(&mut decorated, opts, |(a, line_a), (b, line_b)| {
+        let ordering = compare(a, b).then_with(|| last_resort(line_a, line_b, opts));
+        if opts.reverse { ordering.reverse() } else { ordering }
+    });
+    for (slot, (_, line)) in lines.iter_mut().zip(decorated) {
+        *slot = line;
+    }
+}
+
+fn reversed_if(ordering: Ordering, key: &KeySpec) -> Ordering {
+    if key.reverse { ordering.reverse() } else { ordering }
+}
+
+pub fn sort_in_place(lines: &mut [String], opts: &SortOptions) {
+    match cache_key(opts) {
+        Some(key) if key.mode.is_numeric() => sort_by_cached_key(
+            lines,
+            opts,
+            |line| cached_numeric_value(line, key, opts),
+            |a, b| reversed_if(a.cmp(b), key),
+        ),
+        Some(key) if key.mode == SortMode::String && key.ignore_case => sort_by_cached_key(
+            lines,
+            opts,
+            |line| cached_value(line, key, opts, str::to_lowercase),
+            |a, b| reversed_if(if key.collate { collate(a, b) } else { a.cmp(b) }, key),
+        ),
+        _ => match &opts.json_field {
+            Some(path) => {
+                let extracted = SortOptions { json_field: None, strip_cr: false, ..opts.clone() };
+                sort_by_cached_key(
+                    lines,
+                    opts,
+                    |line| json_key(without_cr(line, opts), path),
+                    |a, b| compare_lines(a, b, &extracted),
+                );
+            }
+            None => sort_slice(lines, opts, |a, b| compare_ordered(a, b, opts)),
+        },
+    }
+}
+
+pub fn sort_lines(mut lines: Vec<String>, opts: &SortOptions) -> Vec<String> {
+    sort_in_place(&mut lines, opts);
+    lines
+}
+
+pub fn find_disorder(lines: &[String], opts: &SortOptions) -> Option<usize> {
+    (1..lines.len()).find(|&i| match compare_ordered(&lines[i - 1], &lines[i], opts) {
+        Ordering::Greater => true,
+        Ordering::Equal => opts.unique,
+        Ordering::Less => false,
+    })
+}
+
+pub fn check_sorted(lines: &[String], opts: &SortOptions) -> bool {
+    find_disorder(lines, opts).is_none()
+}
+
+fn field_spans(line: &str, separator: Option<&str>) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    match separator {
+        Some(sep) => {
+            let mut start = 0;
+            for (i, _) in line.match_indices(sep) {
+                spans.push((start, i));
+                start = i + sep.len();
+            }
+            spans.push((start, line.len()));
+        }
+        None => {
+            let mut start = 0;
+            let mut in_word = false;
+            for (i, c) in line.char_indices() {
+                if c.is_whitespace() && in_word {
+                    spans.push((start, i));
+                    start = i;
+                }
+                in_word = !c.is_whitespace();
+            }
+            if start < line.len() {
+                spans.push((start, line.len()));
+            }
+        }
+    }
+    spans
+}
+
+fn char_offset(line: &str, (from, to): (usize, usize), chars: usize) -> usize {
+    line[from..to].char_indices().nth(chars).map_or(to, |(offset, _)| from + offset)
+}
+
+fn resolve_field(index: isize, count: usize) -> Option<usize> {
+    let count = count as isize;
+    let resolved = if index < 0 { count + index + 1 } else { index };
+    (1..=count).contains(&resolved).then_some(resolved as usize)
+}
+
+fn key_span(line: &str, key: &KeySpec, separator: Option<&str>) -> (usize, usize) {
+    let spans = field_spans(line, separator);
+    let Some(start) = resolve_field(key.start, spans.len()) else {
+        return (line.len(), line.len());
+    };
+    let (mut field_from, field_to) = spans[start - 1];
+    if key.skip_blanks {
+        field_from = field_to - line[field_from..field_to].trim_start().len();
+    }
+    let from = char_offset(line, (field_from, field_to), key.start_char - 1);
+    let to = match key.end.map(|end| (end, resolve_field(end, spans.len()))) {
+        Some((_, Some(end))) if key.end_char == 0 => spans[end - 1].1,
+        Some((_, Some(end))) => char_offset(line, spans[end - 1], key.end_char),
+        Some((end, None)) if end < 0 => from,
+        Some((_, None)) | None => line.len(),
+    };
+    (from, to.max(from))
+}
+
+fn get_column_value<'a>(line: &'a str, key: &KeySpec, separator: Option<&str>) -> &'a str {
+    let (from, to) = key_span(line, key, separator);
+    &line[from..to]
+}
+
+fn marker_span(line: &str, (from, to): (usize, usize), key: &KeySpec) -> (usize, usize) {
+    let from = if key.skip_blanks { to - line[from..to].trim_start().len() } else { from };
+    (from, to)
+}
+
+fn debug_marker(line: &str, (from, to): (usize, usize)) -> String {
+    let mut marker: String = line[..from].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    if from == to {
+        marker.push_str("^ no match for key");
+    } else {
+        marker.extend(line[from..to].chars().map(|_| '_'));
+    }
+    marker
+}
+
+pub fn debug_spans(line: &str, opts: &SortOptions) -> Vec<(usize, usize)> {
+    if opts.json_field.is_some() {
+        return Vec::new();
+    }
+    let line = without_cr(line, opts);
+    if opts.keys.is_empty() {
+        return vec![marker_span(line, (0, line.len()), &opts.global)];
+    }
+    opts.keys
+        .iter()
+        .map(|key| marker_span(line, key_span(line, key, opts.separator.as_deref()), key))
+        .collect()
+}
+
+pub fn debug_markers(line: &str, opts: &SortOptions) -> Vec<String> {
+    debug_spans(line, opts).into_iter().map(|span| debug_marker(line, span)).collect()
+}
+
+fn split_key_field(part: &str) -> (&str, Option<&str>, &str) {
+    let sign = usize::from(part.starts_with('-'));
+    let digits = part[sign..].find(|c: char| !c.is_ascii_digit()).map_or(part.len(), |pos| pos + sign);
+    let (field, rest) = part.split_at(digits);
+    let Some(rest) = rest.strip_prefix('.') else {
+        return (field, None, rest);
+    };
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let (offset, modifiers) = rest.split_at(digits);
+    (field, Some(offset), modifiers)
+}
+
+fn invalid_field_spec(reason: &str, value: &str) -> SortError {
+    SortError::InvalidKey(format!("{}: invalid field specification '{}'", reason, value))
+}
+
+pub fn parse_key_spec(value: &str, global: &KeySpec) -> Result<KeySpec, SortError> {
+    let (start_part, end_part) = match value.split_once(',') {
+        Some((start, end)) => (start, Some(end)),
+        None => (value, None),
+    };
+    let (start, start_char, modifiers) = split_key_field(start_part);
+    if start.is_empty() {
+        return Err(invalid_field_spec("missing start field number", value));
+    }
+    let start = start.parse::<isize>().map_err(|_| invalid_field_spec("invalid field number", value))?;
+    let start_char = match start_char.map(str::parse::<usize>) {
+        None => 1,
+        Some(Ok(offset)) if offset > 0 => offset,
+        Some(Ok(_)) => return Err(invalid_field_spec("character offset is zero", value)),
+        Some(Err(_)) => return Err(invalid_field_spec("invalid character offset", value)),
+    };
+    if start == 0 {
+        return Err(invalid_field_spec("field number is zero", value));
+    }
+    let mut key = KeySpec {
+        start,
+        start_char,
+        end: None,
+        end_char: 0,
+        ..global.clone()
+    };
+    let mut end_modifiers = "";
+    if let Some(end_part) = end_part {
+        let (end, end_char, rest) = split_key_field(end_part);
+        if end.is_empty() {
+            return Err(invalid_field_spec("missing end field number", value));
+        }
+        key.end = Some(end.parse::<isize>().map_err(|_| invalid_field_spec("invalid field number", value))?);
+        key.end_char = match end_char {
+            None | Some("") => 0,
+            Some(offset) => offset.parse::<usize>().map_err(|_| invalid_field_spec("invalid character offset", value))?,
+        };
+        end_modifiers = rest;
+    }
+    if key.end == Some(0) {
+        return Err(invalid_field_spec("field number is zero", value));
+    }
+    if key.end.is_some_and(|end| (end < 0) == (start < 0) && end < start) {
+        return Err(SortError::InvalidKey(format!("invalid key range '{}': end precedes start", value)));
+    }
+
+    if !modifiers.is_empty() || !end_modifiers.is_empty() {
+        key.mode = SortMode::String;
+        key.ignore_case = false;
+        key.skip_blanks = false;
+        key.ignore_nonprinting = false;
+        key.dictionary_order = false;
+    }
+    for modifier in modifiers.chars().chain(end_modifiers.chars()) {
+        match modifier {
+            'b' => key.skip_blanks = true,
+            'f' => key.ignore_case = true,
+            'i' => key.ignore_nonprinting = true,
+            'd' => key.dictionary_order = true,
+            'r' => key.reverse = true,
+            'n' => key.mode = SortMode::Numeric,
+            'M' => key.mode = SortMode::Month,
+            'h' => key.mode = SortMode::Suffix,
+            'g' => key.mode = SortMode::GeneralNumeric,
+            'V' => key.mode = SortMode::Version,
+            _ => {
+                return Err(SortError::InvalidKey(format!("invalid key modifier '{}' in '{}'", modifier, value)));
+            }
+        }
+    }
+    Ok(key)
+}
+
+pub fn parse_key_specs(values: &[&String], global: &KeySpec) -> Result<Vec<KeySpec>, SortError> {
+    let mut keys = Vec::new();
+    for value in values {
+        keys.push(parse_key_spec(value, global)?);
+    }
+    Ok(keys)
+}
+
+pub fn detect_auto_mode(sample: &[String], opts: &SortOptions) -> SortMode {
+    let numeric = KeySpec { mode: SortMode::Numeric, ..opts.keys.first().unwrap_or(&opts.global).clone() };
+    let all_numeric = !sample.is_empty()
+        && sample.iter().all(|line| {
+            let line = key_source(line, opts);
+            let value = match opts.keys.first() {
+                Some(key) => get_column_value(&line, key, opts.separator.as_deref()),
+                None => &line,
+            };
+            let value = transform_key(value, &numeric);
+            let (number, rest) = numeric_prefix(value.trim());
+            !number.is_empty() && rest.is_empty()
+        });
+    if all_numeric { SortMode::Numeric } else { SortMode::String }
+}
+
+pub fn apply_auto_mode(opts: &mut SortOptions, sample: &[String], key_values: &[&String]) -> Result<(), SortError> {
+    opts.global.mode = detect_auto_mode(sample, opts);
+    opts.keys = parse_key_specs(key_values, &opts.global)?;
+    Ok(())
+}
+
+pub fn parse_separator(value: &str) -> Result<char, SortError> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(sep), None) => Ok(sep),
+        (None, _) => Err(SortError::InvalidOption("empty tab".to_string())),
+        _ => Err(SortError::InvalidOption(format!("multi-character tab '{}'", value))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn with_mode(mode: SortMode) -> SortOptions {
+        SortOptions { global: KeySpec { mode, ..KeySpec::default() }, ..SortOptions::default() }
+    }
+
+    #[test]
+    fn sort_lines_orders_text_by_default() {
+        let sorted = sort_lines(lines(&["pear", "apple", "fig"]), &SortOptions::default());
+        assert_eq!(sorted, lines(&["apple", "fig", "pear"]));
+    }
+
+    #[test]
+    fn sort_lines_orders_numbers_by_value() {
+        let sorted = sort_lines(lines(&["10", "9", "-1", "abc"]), &with_mode(SortMode::Numeric));
+        assert_eq!(sorted, lines(&["abc", "-1", "9", "10"]));
+    }
+
+    #[test]
+    fn sort_lines_orders_months_and_suffixes() {
+        let months = sort_lines(lines(&["Mar", "jan", "Feb"]), &with_mode(SortMode::Month));
+        assert_eq!(months, lines(&["jan", "Feb", "Mar"]));
+        let sizes = sort_lines(lines(&["1M", "2K", "512"]), &with_mode(SortMode::Suffix));
+        assert_eq!(sizes, lines(&["512", "2K", "1M"]));
+    }
+
+    #[test]
+    fn sort_lines_applies_keys_and_reverse() {
+        let opts = SortOptions {
+            keys: parse_key_specs(&[&"2n".to_string()], &KeySpec::default()).unwrap(),
+            reverse: true,
+            ..SortOptions::default()
+        };
+        let sorted = sort_lines(lines(&["a 9", "b 10", "c 1"]), &opts);
+        assert_eq!(sorted, lines(&["b 10", "a 9", "c 1"]));
+    }
+
+    #[test]
+    fn out_of_range_radix_treats_every_key_as_invalid() {
+        for radix in [0, 1, 37, u32::MAX] {
+            let sorted = sort_lines(lines(&["b", "10", "a"]), &with_mode(SortMode::Radix(radix)));
+            assert_eq!(sorted, lines(&["10", "a", "b"]));
+        }
+    }
+
+    #[test]
+    fn check_sorted_accepts_sorted_and_rejects_unsorted_lines() {
+        let opts = with_mode(SortMode::Numeric);
+        assert!(check_sorted(&lines(&["1", "2", "10"]), &opts));
+        assert!(!check_sorted(&lines(&["1", "10", "2"]), &opts));
+        assert!(check_sorted(&[], &opts));
+    }
+
+    #[test]
+    fn get_column_value_borrows_the_field_from_the_line() {
+        let line = "a,bc,d";
+        let key = parse_key_spec("2,2", &KeySpec::default()).unwrap();
+        let value = get_column_value(line, &key, Some(","));
+        assert_eq!(value, "bc");
+        assert!(std::ptr::eq(value.as_ptr(), line[2..].as_ptr()));
+    }
+
+    #[test]
+    fn keyed_sort_matches_sorting_by_the_extracted_field() {
+        let input: Vec<String> = (0..200).map(|n| format!("{} {} {}", n % 7, (n * 13) % 17, n)).collect();
+        let opts = SortOptions {
+            keys: parse_key_specs(&[&"2,2".to_string()], &KeySpec::default()).unwrap(),
+            stable: true,
+            ..SortOptions::default()
+        };
+        let mut expected = input.clone();
+        expected.sort_by(|a, b| a.split(' ').nth(1).cmp(&b.split(' ').nth(1)));
+        assert_eq!(sort_lines(input, &opts), expected);
+    }
+
+    #[test]
+    fn check_sorted_rejects_duplicates_when_unique() {
+        let opts = SortOptions { unique: true, ..SortOptions::default() };
+        assert!(!check_sorted(&lines(&["a", "a"]), &opts));
+        assert!(check_sorted(&lines(&["a", "a"]), &SortOptions::default()));
+    }
+
+    #[test]
+    fn bad_keys_are_invalid_key_errors() {
+        for value in ["0", ",3", "3,1", "2x"] {
+            let err = parse_key_spec(value, &KeySpec::default()).err();
+            assert!(matches!(err, Some(SortError::InvalidKey(_))), "-k{value} should be rejected");
+        }
+    }
+
+    #[test]
+    fn bad_separators_are_invalid_option_errors() {
+        assert!(matches!(parse_separator(""), Err(SortError::InvalidOption(_))));
+        assert!(matches!(parse_separator("ab"), Err(SortError::InvalidOption(_))));
+    }
+
+    #[test]
+    fn a_missing_file_is_an_io_error() {
+        let err = SortError::from(std::fs::File::open("no-such-file.txt").unwrap_err());
+        assert!(matches!(&err, SortError::Io(io_err) if io_err.kind() == io::ErrorKind::NotFound));
+        assert_eq!(err.to_string(), "No such file or directory");
+    }
+
+    #[test]
+    fn wrapped_sort_errors_keep_their_variant() {
+        let wrapped = io::Error::new(io::ErrorKind::InvalidData, SortError::Disorder("-:2: disorder: a".to_string()));
+        assert!(matches!(SortError::from(wrapped), SortError::Disorder(_)));
+    }
+}
@@ -1,234 +1,1523 @@
-use clap::{Arg, ArgAction, Command};
-use std::fs::File;
-use std::io::{self, BufRead, Write};
+use clap::parser::{ValueSource, ValuesRef};
+use clap::error::ErrorKind;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use sort::{apply_auto_mode, compare_lines, compare_ordered, debug_markers, debug_spans, describe_io_error, find_disorder, key_number, key_text, parse_key_specs, parse_separator, sort_in_place, valid_json, EmptyKeys, KeySpec, LengthUnit, SortError, SortMode, SortOptions};
+use std::any::Any;
+use std::cmp::Ordering;
+use std::cell::Cell;
+use std::collections::BinaryHeap;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const DEFAULT_BUFFER_SIZE: usize = 256 << 20;
 
-fn sort_by_numeric(lines: &mut Vec<String>, column: Option<usize>) {
-    lines.sort_unstable_by(|a, b| {
-        let a = get_column_value(a, column);
-        let b = get_column_value(b, column);
-        let num_a: f64 = a.parse().unwrap_or(f64::MIN);
-        let num_b: f64 = b.parse().unwrap_or(f64::MIN);
-        num_a.partial_cmp(&num_b).unwrap()
-    });
+const DEFAULT_BATCH_SIZE: usize = 16;
+
+const AUTO_SAMPLE_LINES: usize = 100;
+
+const PROGRESS_INTERVAL: usize = 1_000_000;
+
+struct MergeEntry<'a> {
+    line: String,
+    source: usize,
+    opts: &'a SortOptions,
 }
 
-fn sort_by_month(lines: &mut Vec<String>, column: Option<usize>) {
-    lines.sort_unstable_by_key(|line| {
-        let value = get_column_value(line, column);
-        MONTHS.iter().position(|&month| value.contains(month)).unwrap_or(13)
-    });
+impl PartialEq for MergeEntry<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
 }
 
-fn parse_with_suffix(s: &String) -> f64 {
-    let len = s.len();
-    if len == 0 {
-        return f64::MIN;
+impl Eq for MergeEntry<'_> {}
+
+impl PartialOrd for MergeEntry<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    if len == 1 {
-        return s.parse().unwrap_or(f64::MIN);
+impl Ord for MergeEntry<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_ordered(&other.line, &self.line, self.opts).then_with(|| other.source.cmp(&self.source))
     }
-    let number_part: f64 = s[..len-1].parse().unwrap_or(0.0);
-    let suffix = &s[len-1..];
-    match suffix {
-        "K"|"k" => number_part * 1_000.0,
-        "M"|"m" => number_part * 1_000_000.0,
-        "G"|"g" => number_part * 1_000_000_000.0,
-        _ => number_part,
+}
+
+struct MergeIter<'a, I: Iterator<Item = io::Result<String>>> {
+    sources: Vec<I>,
+    heap: BinaryHeap<MergeEntry<'a>>,
+}
+
+impl<'a, I: Iterator<Item = io::Result<String>>> MergeIter<'a, I> {
+    fn new(mut sources: Vec<I>, opts: &'a SortOptions) -> io::Result<Self> {
+        let mut heap = BinaryHeap::new();
+        for (source, iter) in sources.iter_mut().enumerate() {
+            if let Some(line) = iter.next().transpose()? {
+                heap.push(MergeEntry { line, source, opts });
+            }
+        }
+        Ok(MergeIter { sources, heap })
     }
 }
 
-fn sort_by_suffix(lines: &mut Vec<String>, column: Option<usize>) {
-    lines.sort_unstable_by(|a, b| {
-        let a = get_column_value(a, column);
-        let b = get_column_value(b, column);
-        let num_a = parse_with_suffix(&a);
-        let num_b = parse_with_suffix(&b);
-        num_a.partial_cmp(&num_b).unwrap()
-    });
+impl<I: Iterator<Item = io::Result<String>>> Iterator for MergeIter<'_, I> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        let entry = self.heap.pop()?;
+        match self.sources[entry.source].next() {
+            Some(Ok(line)) => self.heap.push(MergeEntry { line, source: entry.source, opts: entry.opts }),
+            Some(Err(e)) => return Some(Err(e)),
+            None => {}
+        }
+        Some(Ok(entry.line))
+    }
 }
 
-fn sort_by_string(lines: &mut Vec<String>, column: Option<usize>) {
-    lines.sort_unstable_by_key(|line| get_column_value(line, column));
+struct MergeInput<'a, I: Iterator<Item = io::Result<String>>> {
+    records: I,
+    name: &'a str,
+    line: usize,
+    previous: Option<String>,
+    check_order: Option<&'a SortOptions>,
+    json_strict: bool,
 }
 
-fn check_sorted_by_numeric(lines: &Vec<String>, column: Option<usize>, reversed: bool) -> bool {
-    for i in 1..lines.len() {
-        let a = get_column_value(&lines[i], column);
-        let b = get_column_value(&lines[i-1], column);
-        let num_a: f64 = a.parse().unwrap_or(f64::MIN);
-        let num_b: f64 = b.parse().unwrap_or(f64::MIN);
+impl<I: Iterator<Item = io::Result<String>>> Iterator for MergeInput<'_, I> {
+    type Item = io::Result<String>;
 
-        if !reversed {
-            if num_a < num_b {
-                return false;
-            }
+    fn next(&mut self) -> Option<io::Result<String>> {
+        let record = match self.records.next()? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        self.line += 1;
+        if self.json_strict && !valid_json(&record) {
+            let error = SortError::Parse(format!("{}:{}: invalid JSON", self.name, self.line));
+            return Some(Err(io::Error::new(io::ErrorKind::InvalidData, error)));
         }
-        else {
-            if num_b < num_a {
-                return false;
+        if let Some(opts) = self.check_order {
+            if self.previous.as_ref().is_some_and(|previous| compare_ordered(previous, &record, opts) == Ordering::Greater) {
+                let error = SortError::Disorder(format!("{}:{}: disorder: {}", self.name, self.line, record));
+                return Some(Err(io::Error::new(io::ErrorKind::InvalidData, error)));
             }
+            self.previous = Some(record.clone());
         }
+        Some(Ok(record))
     }
-    true
 }
 
-fn check_sorted_by_month(lines: &Vec<String>, column: Option<usize>, reversed: bool) -> bool {
-    for i in 1..lines.len() {
-        let a = get_column_value(&lines[i], column);
-        let b = get_column_value(&lines[i-1], column);
-        let month_pos_a: usize = MONTHS.iter().position(|&month| a.contains(month)).unwrap_or(13);
-        let month_pos_b: usize = MONTHS.iter().position(|&month| b.contains(month)).unwrap_or(13);
+fn merge_inputs<'a, I: Iterator<Item = io::Result<String>>>(
+    sources: Vec<(&'a str, I)>,
+    check_order: Option<&'a SortOptions>,
+    json_strict: bool,
+) -> Vec<MergeInput<'a, I>> {
+    sources
+        .into_iter()
+        .map(|(name, records)| MergeInput { records, name, line: 0, previous: None, check_order, json_strict })
+        .collect()
+}
 
-        if !reversed {
-            if month_pos_a < month_pos_b {
-                return false;
-            }
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+fn random_seed(source: Option<&str>) -> io::Result<u64> {
+    let Some(path) = source else {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        return Ok(nanos ^ u64::from(process::id()).rotate_left(32));
+    };
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(4096).read_to_end(&mut bytes))
+        .map_err(|e| io::Error::new(e.kind(), format!("open failed: {}: {}", path, describe_io_error(&e))))?;
+    if bytes.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("{}: end of file", path)));
+    }
+    Ok(fnv1a(&bytes))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3))
+}
+
+fn shuffle_lines(lines: &mut Vec<String>, opts: &SortOptions, seed: u64) {
+    sort_in_place(lines, opts);
+    let mut groups: Vec<(u64, Vec<String>)> = Vec::new();
+    for group in lines.chunk_by_mut(|a, b| compare_lines(a, b, opts) == Ordering::Equal) {
+        let hash = SplitMix64 { state: fnv1a(key_text(&group[0], opts).as_bytes()) ^ seed }.next_u64();
+        groups.push((hash, group.iter_mut().map(std::mem::take).collect()));
+    }
+    groups.sort_by(|(a, group_a), (b, group_b)| a.cmp(b).then_with(|| compare_lines(&group_a[0], &group_b[0], opts)));
+    *lines = groups.into_iter().flat_map(|(_, group)| group).collect();
+}
+
+fn locate_line<'a>(sources: &[(&'a str, usize)], index: usize) -> (&'a str, usize) {
+    let mut first = 0;
+    for &(name, end) in sources {
+        if index < end {
+            return (name, index - first + 1);
         }
-        else {
-            if month_pos_b < month_pos_a {
-                return false;
-            }
+        first = end;
+    }
+    ("-", index + 1)
+}
+
+fn open_input(path: &str) -> io::Result<Box<dyn BufRead>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    match File::open(path) {
+        Ok(file) => decompress_input(path, io::BufReader::new(file)),
+        Err(e) => Err(io::Error::new(e.kind(), format!("cannot open '{}': {}", path, describe_io_error(&e)))),
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn decompress_input(path: &str, mut reader: io::BufReader<File>) -> io::Result<Box<dyn BufRead>> {
+    if path.ends_with(".gz") || reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        let decoder = flate2::bufread::MultiGzDecoder::new(reader);
+        return Ok(Box::new(io::BufReader::new(decoder)));
+    }
+    Ok(Box::new(reader))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn decompress_input(_path: &str, reader: io::BufReader<File>) -> io::Result<Box<dyn BufRead>> {
+    Ok(Box::new(reader))
+}
+
+trait OutputSink: Write {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+impl OutputSink for File {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OutputSink for io::StdoutLock<'static> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OutputSink for io::Sink {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl OutputSink for flate2::write::GzEncoder<File> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        flate2::write::GzEncoder::finish(*self).map(drop)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl OutputSink for zstd::Encoder<'static, File> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        zstd::Encoder::finish(*self).map(drop)
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_output(file: File) -> io::Result<Box<dyn OutputSink>> {
+    Ok(Box::new(flate2::write::GzEncoder::new(file, flate2::Compression::default())))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_output(_file: File) -> io::Result<Box<dyn OutputSink>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "writing .gz output requires the gzip feature"))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_output(file: File) -> io::Result<Box<dyn OutputSink>> {
+    Ok(Box::new(zstd::Encoder::new(file, 0)?))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_output(_file: File) -> io::Result<Box<dyn OutputSink>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "writing .zst output requires the zstd feature"))
+}
+
+fn compress_output(path: &str, file: File) -> io::Result<Box<dyn OutputSink>> {
+    if path.ends_with(".gz") {
+        gzip_output(file)
+    } else if path.ends_with(".zst") {
+        zstd_output(file)
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+fn read_file_list(list: &str) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for (index, name) in RecordReader::new(open_input(list)?, list, b'\0').enumerate() {
+        let name = name?;
+        if name.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{}:{}: invalid zero-length file name", list, index + 1)));
+        }
+        if list == "-" && name == "-" {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "when reading file names from stdin, no file name of '-' allowed"));
         }
+        names.push(name);
     }
-    true
+    Ok(names)
+}
+
+struct RecordReader {
+    reader: Box<dyn BufRead>,
+    name: String,
+    delimiter: u8,
+    max_line_bytes: usize,
+    crlf_count: usize,
+    has_bom: bool,
+    strip_bom: bool,
+    warned: bool,
 }
 
-fn check_sorted_by_suffix(lines: &Vec<String>, column: Option<usize>, reversed: bool) -> bool {
-    for i in 1..lines.len() {
-        let a = get_column_value(&lines[i], column);
-        let b = get_column_value(&lines[i-1], column);
-        let num_a: f64 = parse_with_suffix(&a);
-        let num_b: f64 = parse_with_suffix(&b);
+impl RecordReader {
+    fn new(reader: Box<dyn BufRead>, name: &str, delimiter: u8) -> Self {
+        RecordReader {
+            reader,
+            name: name.to_string(),
+            delimiter,
+            max_line_bytes: usize::MAX,
+            crlf_count: 0,
+            has_bom: false,
+            strip_bom: false,
+            warned: false,
+        }
+    }
 
-        if !reversed {
-            if num_a < num_b {
-                return false;
+    fn next_record(&mut self) -> io::Result<Option<String>> {
+        let mut buffer = Vec::new();
+        let limit = self.max_line_bytes.saturating_add(1) as u64;
+        if self.reader.by_ref().take(limit).read_until(self.delimiter, &mut buffer)? == 0 {
+            return Ok(None);
+        }
+        if buffer.len() > self.max_line_bytes && buffer.last() != Some(&self.delimiter) {
+            let error = SortError::Parse(format!("{}: line exceeds {} bytes", self.name, self.max_line_bytes));
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        if self.strip_bom {
+            self.strip_bom = false;
+            if buffer.starts_with(b"\xef\xbb\xbf") {
+                buffer.drain(..3);
+                self.has_bom = true;
+            }
+        }
+        if buffer.last() == Some(&self.delimiter) {
+            buffer.pop();
+            if self.delimiter == b'\n' && buffer.last() == Some(&b'\r') {
+                self.crlf_count += 1;
             }
         }
-        else {
-            if num_b < num_a {
-                return false;
+        match String::from_utf8(buffer) {
+            Ok(line) => Ok(Some(line)),
+            Err(e) => {
+                if !self.warned {
+                    eprintln!("sort: {}: invalid UTF-8 input, replacing undecodable bytes", self.name);
+                    self.warned = true;
+                }
+                Ok(Some(String::from_utf8_lossy(e.as_bytes()).into_owned()))
             }
         }
     }
-    true
 }
 
-fn get_column_value(line: &str, column: Option<usize>) -> String {
-    column
-        .and_then(|col| line.split_whitespace().nth(col - 1))
-        .unwrap_or(line)
-        .to_string()
+impl Iterator for RecordReader {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        self.next_record().transpose()
+    }
+}
+
+struct TempFile {
+    path: PathBuf,
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn compress_failed(program: &str, err: &io::Error) -> io::Error {
+    io::Error::new(err.kind(), format!("couldn't execute compress program '{}': {}", program, describe_io_error(err)))
+}
+
+fn check_compress_status(program: &str, status: process::ExitStatus) -> io::Result<()> {
+    if status.success() {
+        return Ok(());
+    }
+    Err(io::Error::other(format!("compress program '{}' failed: {}", program, status)))
+}
+
+struct DecompressReader {
+    program: String,
+    child: process::Child,
+    output: process::ChildStdout,
+}
+
+impl Read for DecompressReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.output.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            check_compress_status(&self.program, self.child.wait()?)?;
+        }
+        Ok(read)
+    }
+}
+
+struct SpillConfig<'a> {
+    directory: PathBuf,
+    next_index: Cell<usize>,
+    delimiter: u8,
+    compress_program: Option<&'a str>,
+}
+
+fn temp_file_error(directory: &Path, err: &io::Error) -> io::Error {
+    io::Error::new(
+        err.kind(),
+        format!("cannot create temporary file in '{}': {}", directory.display(), describe_io_error(err)),
+    )
+}
+
+fn check_temp_directory(directory: &Path) -> io::Result<()> {
+    let probe = TempFile {
+        path: directory.join(format!("sort.{}.probe", process::id())),
+    };
+    File::create(&probe.path).map(drop).map_err(|e| temp_file_error(directory, &e))
+}
+
+fn write_run<I: Iterator<Item = io::Result<String>>>(records: I, config: &SpillConfig) -> io::Result<TempFile> {
+    let (delimiter, compress_program) = (config.delimiter, config.compress_program);
+    let index = config.next_index.replace(config.next_index.get() + 1);
+    let path = config.directory.join(format!("sort.{}.{}", process::id(), index));
+    let spill = TempFile { path };
+    let file = File::create(&spill.path).map_err(|e| temp_file_error(&config.directory, &e))?;
+    let mut child = None;
+    let sink: Box<dyn Write> = match compress_program {
+        Some(program) => {
+            let mut spawned = process::Command::new(program)
+                .stdin(process::Stdio::piped())
+                .stdout(file)
+                .spawn()
+                .map_err(|e| compress_failed(program, &e))?;
+            let stdin = spawned.stdin.take().expect("compress program stdin is piped");
+            child = Some((program, spawned));
+            Box::new(stdin)
+        }
+        None => Box::new(file),
+    };
+    let mut writer = io::BufWriter::new(sink);
+    for line in records {
+        writer.write_all(line?.as_bytes())?;
+        writer.write_all(&[delimiter])?;
+    }
+    writer.flush()?;
+    drop(writer);
+    if let Some((program, mut child)) = child {
+        check_compress_status(program, child.wait()?)?;
+    }
+    Ok(spill)
+}
+
+fn spill_run(lines: &mut Vec<String>, opts: &SortOptions, config: &SpillConfig) -> io::Result<TempFile> {
+    sort_in_place(lines, opts);
+    write_run(lines.drain(..).map(Ok), config)
+}
+
+fn merge_runs(runs: Vec<TempFile>, opts: &SortOptions, config: &SpillConfig, batch_size: usize) -> io::Result<Vec<TempFile>> {
+    let mut merged = Vec::new();
+    for batch in runs.chunks(batch_size) {
+        let readers = batch.iter().map(|run| open_run(run, config)).collect::<io::Result<Vec<_>>>()?;
+        merged.push(write_run(MergeIter::new(readers, opts)?, config)?);
+    }
+    Ok(merged)
+}
+
+fn open_run(spill: &TempFile, config: &SpillConfig) -> io::Result<RecordReader> {
+    let (delimiter, compress_program) = (config.delimiter, config.compress_program);
+    let file = File::open(&spill.path)?;
+    let name = spill.path.display().to_string();
+    let input: Box<dyn BufRead> = match compress_program {
+        Some(program) => {
+            let mut child = process::Command::new(program)
+                .arg("-d")
+                .stdin(file)
+                .stdout(process::Stdio::piped())
+                .spawn()
+                .map_err(|e| compress_failed(program, &e))?;
+            let output = child.stdout.take().expect("compress program stdout is piped");
+            Box::new(io::BufReader::new(DecompressReader { program: program.to_string(), child, output }))
+        }
+        None => Box::new(io::BufReader::new(file)),
+    };
+    Ok(RecordReader::new(input, &name, delimiter))
+}
+
+struct AtomicOutput {
+    target: PathBuf,
+    temp: TempFile,
+    hard_linked: bool,
 }
 
-fn main() -> io::Result<()> {
-    let matches = Command::new("sort")
+impl AtomicOutput {
+    fn new(path: &str) -> Option<AtomicOutput> {
+        let metadata = fs::metadata(path);
+        let target = match &metadata {
+            Ok(metadata) if !metadata.is_file() => return None,
+            Ok(_) => fs::canonicalize(path).ok()?,
+            Err(_) if fs::symlink_metadata(path).is_ok() => return None,
+            Err(_) => PathBuf::from(path),
+        };
+        let name = target.file_name()?.to_string_lossy().into_owned();
+        let temp = TempFile { path: target.with_file_name(format!(".{}.{}.tmp", name, process::id())) };
+        let hard_linked = metadata.as_ref().is_ok_and(is_hard_linked);
+        Some(AtomicOutput { target, temp, hard_linked })
+    }
+
+    fn commit(&self) -> io::Result<()> {
+        if self.hard_linked {
+            let mut target = fs::OpenOptions::new().write(true).truncate(true).open(&self.target)?;
+            io::copy(&mut File::open(&self.temp.path)?, &mut target)?;
+            return Ok(());
+        }
+        if let Ok(metadata) = fs::metadata(&self.target) {
+            copy_ownership(&metadata, &self.temp.path);
+            fs::set_permissions(&self.temp.path, metadata.permissions())?;
+        }
+        fs::rename(&self.temp.path, &self.target)
+    }
+}
+
+#[cfg(unix)]
+fn is_hard_linked(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn is_hard_linked(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn copy_ownership(metadata: &fs::Metadata, path: &Path) {
+    use std::os::unix::fs::{chown, MetadataExt};
+    if chown(path, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+        let _ = chown(path, None, Some(metadata.gid()));
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_metadata: &fs::Metadata, _path: &Path) {}
+
+fn parse_buffer_size(value: &str) -> Result<usize, SortError> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier: usize = match unit {
+        "b" => 1,
+        "" | "K" | "k" => 1 << 10,
+        "M" | "m" => 1 << 20,
+        "G" | "g" => 1 << 30,
+        _ => 0,
+    };
+    match number.parse::<usize>() {
+        Ok(size) if multiplier != 0 && size != 0 => Ok(size.saturating_mul(multiplier)),
+        _ => Err(SortError::InvalidOption(format!("invalid -S argument '{}'", value))),
+    }
+}
+
+struct OutputFormat<'a> {
+    line_ending: &'a str,
+    separator: Option<&'a str>,
+    unique: bool,
+    count: bool,
+    debug: bool,
+    color: bool,
+    sum: bool,
+    progress: bool,
+}
+
+fn write_item<W: Write>(output: &mut W, item: &str, format: &OutputFormat, emitted: &mut usize) -> io::Result<()> {
+    if let Some(separator) = format.separator.filter(|_| *emitted > 0) {
+        output.write_all(separator.as_bytes())?;
+    }
+    let item = if format.line_ending == "\r\n" { item.strip_suffix('\r').unwrap_or(item) } else { item };
+    output.write_all(item.as_bytes())?;
+    if format.separator.is_none() {
+        output.write_all(format.line_ending.as_bytes())?;
+    }
+    *emitted += 1;
+    Ok(())
+}
+
+fn highlight_keys(record: &str, opts: &SortOptions) -> String {
+    let mut spans: Vec<(usize, usize)> = debug_spans(record, opts).into_iter().filter(|(from, to)| from < to).collect();
+    spans.sort_unstable();
+    let mut highlighted = String::new();
+    let mut position = 0;
+    for (from, to) in spans {
+        let from = from.max(position);
+        if from >= to {
+            continue;
+        }
+        highlighted.push_str(&record[position..from]);
+        highlighted.push_str("\x1b[1;4;33m");
+        highlighted.push_str(&record[from..to]);
+        highlighted.push_str("\x1b[0m");
+        position = to;
+    }
+    highlighted.push_str(&record[position..]);
+    highlighted
+}
+
+fn write_record<W: Write>(
+    output: &mut W,
+    record: &str,
+    count: Option<usize>,
+    format: &OutputFormat,
+    opts: &SortOptions,
+    emitted: &mut usize,
+) -> io::Result<()> {
+    let prefix = count.map_or_else(String::new, |count| format!("{:>7} ", count));
+    if format.debug && format.color {
+        return write_item(output, &format!("{}{}", prefix, highlight_keys(record, opts)), format, emitted);
+    }
+    write_item(output, &format!("{}{}", prefix, record), format, emitted)?;
+    if format.debug {
+        for marker in debug_markers(record, opts) {
+            output.write_all(" ".repeat(prefix.len()).as_bytes())?;
+            output.write_all(marker.as_bytes())?;
+            output.write_all(format.line_ending.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn format_total(total: f64) -> String {
+    if total.fract() == 0.0 || !total.is_finite() {
+        return total.to_string();
+    }
+    format!("{:.14e}", total).parse::<f64>().unwrap_or(total).to_string()
+}
+
+fn write_records<W: Write, I: Iterator<Item = io::Result<String>>>(
+    output: &mut W,
+    records: I,
+    format: &OutputFormat,
+    opts: &SortOptions,
+) -> io::Result<usize> {
+    let mut previous: Option<String> = None;
+    let mut count = 0;
+    let mut total = 0.0;
+    let mut written = 0;
+    let mut emitted = 0;
+    for record in records {
+        let record = record?;
+        if format.sum {
+            total += key_number(&record, opts).unwrap_or(0.0);
+        }
+        let collapse = format.unique || format.count;
+        if collapse && previous.as_ref().is_some_and(|prev| compare_lines(prev, &record, opts) == Ordering::Equal) {
+            count += 1;
+            continue;
+        }
+        if !format.count {
+            write_record(output, &record, None, format, opts, &mut emitted)?;
+        } else if let Some(prev) = previous.take() {
+            write_record(output, &prev, Some(count), format, opts, &mut emitted)?;
+        }
+        written += 1;
+        if format.progress && written % PROGRESS_INTERVAL == 0 {
+            eprintln!("sort: wrote {} lines", written);
+        }
+        previous = Some(record);
+        count = 1;
+    }
+    if let Some(prev) = previous.filter(|_| format.count) {
+        write_record(output, &prev, Some(count), format, opts, &mut emitted)?;
+    }
+    if format.sum {
+        write_item(output, &format_total(total), format, &mut emitted)?;
+    }
+    if format.separator.is_some() && emitted > 0 {
+        output.write_all(format.line_ending.as_bytes())?;
+    }
+    Ok(written)
+}
+
+fn command() -> Command {
+    Command::new("sort")
+        .about("Write sorted concatenation of all FILE(s) to standard output")
+        .after_help("Options in the SORT_OPTIONS environment variable are defaults: an option given on the command line replaces the same option from SORT_OPTIONS and any option it conflicts with, such as -M replacing -n.")
         .disable_help_flag(true)
+        .args_override_self(true)
         .arg(Arg::new("filename")
-            .required(true)
-            .index(1))
+            .index(1)
+            .num_args(1..)
+            .value_name("FILE")
+            .help("Files to sort; reads standard input when absent or \"-\""))
 
         .arg(Arg::new("k")
             .short('k')
-            .default_value("1")
-            .num_args(1))
+            .action(ArgAction::Append)
+            .num_args(1)
+            .allow_hyphen_values(true)
+            .value_name("KEYDEF")
+            .help("Sort by the key spanning fields START[,END] with optional ordering modifiers; negative fields count from the end"))
 
         .arg(Arg::new("n")
             .short('n')
             .action(ArgAction::SetTrue)
-            .conflicts_with_all(&["M", "h"]))
+            .conflicts_with_all(["M", "h"])
+            .help("Compare according to string numerical value"))
 
         .arg(Arg::new("r")
             .short('r')
-            .action(ArgAction::SetTrue))
+            .action(ArgAction::SetTrue)
+            .help("Reverse the overall comparison; a key's own r modifier reverses only that key"))
 
         .arg(Arg::new("u")
             .short('u')
-            .action(ArgAction::SetTrue))
+            .action(ArgAction::SetTrue)
+            .help("Output only the first of lines with equal keys, after ordering by the active comparison"))
 
         .arg(Arg::new("M")
             .short('M')
             .action(ArgAction::SetTrue)
-            .conflicts_with("h"))
+            .conflicts_with("h")
+            .help("Compare as month names (JAN < ... < DEC)"))
 
         .arg(Arg::new("b")
             .short('b')
-            .action(ArgAction::SetTrue))
+            .long("ignore-leading-blanks")
+            .action(ArgAction::SetTrue)
+            .help("Ignore leading blanks in keys"))
 
         .arg(Arg::new("c")
             .short('c')
+            .long("check")
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("diagnose-first")
+            .value_parser(["diagnose-first", "quiet", "silent"])
+            .value_name("WHEN")
+            .help("Check whether input is sorted and report the first disorder"))
+
+        .arg(Arg::new("C")
+            .short('C')
             .action(ArgAction::SetTrue)
-            .requires_all(&["M", "h", "n"]))
+            .conflicts_with("c")
+            .help("Like -c, but only report through the exit status"))
 
         .arg(Arg::new("h")
             .short('h')
-            .action(ArgAction::SetTrue))
-        .get_matches();
+            .action(ArgAction::SetTrue)
+            .help("Compare human readable numbers (e.g. 2K, 1Gi)"))
 
-    let filename = matches.get_one::<String>("filename").unwrap();
-    let file = File::open(filename)?;
-    let reader = io::BufReader::new(file);
-    let mut lines: Vec<String> = reader.lines().map(|l| l.unwrap()).collect();
+        .arg(Arg::new("g")
+            .short('g')
+            .long("general-numeric-sort")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["n", "M", "h"])
+            .help("Compare according to general numerical value"))
 
-    let reverse = matches.get_flag("r");
-    let unique = matches.get_flag("u");
-    let numeric = matches.get_flag("n");
-    let month = matches.get_flag("M");
-    let suffix = matches.get_flag("h");
-    let check_sorted = matches.get_flag("c");
-    let ignore_spaces = matches.get_flag("b");
-    let column = matches.get_one::<String>("k").and_then(|c| c.parse::<usize>().ok());
+        .arg(Arg::new("V")
+            .short('V')
+            .long("version-sort")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["n", "M", "h", "g"])
+            .help("Natural sort of version numbers within text"))
 
-    if ignore_spaces {
-        lines = lines.into_iter().map(|line| line.trim_end().to_string()).collect();
-    }
+        .arg(Arg::new("f")
+            .short('f')
+            .long("ignore-case")
+            .action(ArgAction::SetTrue)
+            .help("Ignore case when comparing"))
 
-    if check_sorted {
-        let mut sorted = true;
+        .arg(Arg::new("s")
+            .short('s')
+            .long("stable")
+            .action(ArgAction::SetTrue)
+            .help("Keep the input order of lines with equal keys instead of comparing whole lines as a last resort"))
 
-        if numeric {
-            sorted = check_sorted_by_numeric(&lines, column, reverse);
-        }
+        .arg(Arg::new("t")
+            .short('t')
+            .long("field-separator")
+            .num_args(1)
+            .value_name("SEP")
+            .help("Use SEP instead of whitespace to split fields"))
 
-        if month {
-            sorted = check_sorted_by_month(&lines, column, reverse);
-        }
+        .arg(Arg::new("empty-keys")
+            .long("empty-keys")
+            .num_args(1)
+            .value_name("WHERE")
+            .value_parser(["first", "last"])
+            .help("Order lines whose key is empty first or last, even with -r or a key's r modifier"))
 
-        if suffix {
-            sorted = check_sorted_by_suffix(&lines, column, reverse);
-        }
+        .arg(Arg::new("field-separator-str")
+            .long("field-separator-str")
+            .num_args(1)
+            .value_name("STR")
+            .conflicts_with("t")
+            .help("Split fields on the literal string STR, which may be longer than one character"))
+
+        .arg(Arg::new("key-width")
+            .long("key-width")
+            .num_args(1)
+            .value_name("N")
+            .help("Compare at most the first N characters of each key"))
+
+        .arg(Arg::new("currency")
+            .long("currency")
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("$€£¥")
+            .value_name("SYMBOLS")
+            .help("Ignore leading currency SYMBOLS in numeric keys (default $€£¥); output is unchanged"))
+
+        .arg(Arg::new("thousands-sep")
+            .long("thousands-sep")
+            .num_args(1)
+            .value_name("SEP")
+            .help("Ignore SEP inside numeric keys, so that 1,234 compares as 1234"))
+
+        .arg(Arg::new("collate")
+            .long("collate")
+            .action(ArgAction::SetTrue)
+            .help("Compare text with Unicode collation so accented letters sort beside their base letters"))
+
+        .arg(Arg::new("i")
+            .short('i')
+            .long("ignore-nonprinting")
+            .action(ArgAction::SetTrue)
+            .help("Consider only printable characters"))
+
+        .arg(Arg::new("d")
+            .short('d')
+            .long("dictionary-order")
+            .action(ArgAction::SetTrue)
+            .help("Consider only blanks and alphanumeric characters"))
+
+        .arg(Arg::new("z")
+            .short('z')
+            .long("zero-terminated")
+            .action(ArgAction::SetTrue)
+            .help("Line delimiter is NUL, not newline"))
+
+        .arg(Arg::new("m")
+            .short('m')
+            .long("merge")
+            .action(ArgAction::SetTrue)
+            .help("Merge already sorted files; do not sort"))
+
+        .arg(Arg::new("no-check-order")
+            .long("no-check-order")
+            .action(ArgAction::SetTrue)
+            .requires("m")
+            .help("Do not verify that merge inputs are already sorted"))
+
+        .arg(Arg::new("S")
+            .short('S')
+            .long("buffer-size")
+            .num_args(1)
+            .value_name("SIZE")
+            .help("Use SIZE bytes of memory before spilling sorted runs to temporary files"))
+
+        .arg(Arg::new("max-line-bytes")
+            .long("max-line-bytes")
+            .num_args(1)
+            .value_name("BYTES")
+            .help("Fail instead of buffering any input line longer than BYTES"))
+
+        .arg(Arg::new("T")
+            .short('T')
+            .long("temporary-directory")
+            .num_args(1)
+            .value_name("DIR")
+            .help("Use DIR for temporary files instead of $TMPDIR or the system default"))
+
+        .arg(Arg::new("batch-size")
+            .long("batch-size")
+            .num_args(1)
+            .value_name("NMERGE")
+            .help("Merge at most NMERGE temporary files at once, in several passes if needed"))
+
+        .arg(Arg::new("compress-program")
+            .long("compress-program")
+            .num_args(1)
+            .value_name("PROG")
+            .help("Compress temporary files with PROG; decompress them with PROG -d"))
+
+        .arg(Arg::new("parallel")
+            .long("parallel")
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("0")
+            .value_name("N")
+            .help("Sort with N threads; all available cores when N is 0 or omitted"))
+
+        .arg(Arg::new("R")
+            .short('R')
+            .long("random-sort")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["n", "M", "h", "g", "V"])
+            .help("Shuffle lines into a random order"))
+
+        .arg(Arg::new("L")
+            .short('L')
+            .long("length-sort")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["n", "M", "h", "g", "V", "R", "sort"])
+            .help("Order lines by length, shortest first; ties fall back to text order"))
+
+        .arg(Arg::new("length-unit")
+            .long("length-unit")
+            .num_args(1)
+            .value_name("UNIT")
+            .value_parser(["bytes", "chars"])
+            .requires("L")
+            .help("Measure -L lengths in bytes (default) or characters"))
+
+        .arg(Arg::new("radix")
+            .long("radix")
+            .num_args(1)
+            .value_name("BASE")
+            .conflicts_with_all(["n", "M", "h", "g", "V", "R", "sort", "L"])
+            .help("Compare keys as integers in BASE (2-36); base 16 accepts a 0x prefix"))
+
+        .arg(Arg::new("sort")
+            .long("sort")
+            .num_args(1)
+            .value_name("WORD")
+            .value_parser(["general-numeric", "human-numeric", "month", "numeric", "random", "version"])
+            .conflicts_with_all(["n", "M", "h", "g", "V", "R"])
+            .help("Sort according to WORD: general-numeric -g, human-numeric -h, month -M, numeric -n, random -R, version -V"))
+
+        .arg(Arg::new("random-source")
+            .long("random-source")
+            .num_args(1)
+            .value_name("FILE")
+            .help("Seed the random order from the contents of FILE"))
+
+        .arg(Arg::new("debug")
+            .long("debug")
+            .action(ArgAction::SetTrue)
+            .help("Underline the part of each line used as the sort key"))
+
+        .arg(Arg::new("color")
+            .long("color")
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("auto")
+            .value_name("WHEN")
+            .value_parser(["auto", "always", "never"])
+            .help("With --debug, highlight key spans in color instead of underlining them: auto (default), always or never"))
+
+        .arg(Arg::new("progress")
+            .long("progress")
+            .action(ArgAction::SetTrue)
+            .help("Report lines read, runs spilled and lines written on standard error"))
+
+        .arg(Arg::new("files0-from")
+            .long("files0-from")
+            .num_args(1)
+            .value_name("F")
+            .conflicts_with("filename")
+            .help("Read input file names from F, separated by NUL; standard input when F is -"))
+
+        .arg(Arg::new("auto")
+            .long("auto")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["n", "M", "h", "g", "V", "R", "sort", "radix", "L"])
+            .help("Sort numerically if the first lines of the key are all numbers as -n reads them, otherwise as text"))
+
+        .arg(Arg::new("count")
+            .long("count")
+            .action(ArgAction::SetTrue)
+            .help("Collapse lines with equal keys like -u and prefix each with its number of occurrences"))
+
+        .arg(Arg::new("sum")
+            .long("sum")
+            .action(ArgAction::SetTrue)
+            .help("With -n, -g, -h or --radix, append a line with the total of the sort keys of all input lines, including ones collapsed by -u or --count"))
+
+        .arg(Arg::new("json")
+            .long("json")
+            .action(ArgAction::SetTrue)
+            .requires("field")
+            .help("Parse each line as JSON and sort by the value at --field"))
+
+        .arg(Arg::new("field")
+            .long("field")
+            .num_args(1)
+            .value_name("PATH")
+            .requires("json")
+            .help("Dotted path of the JSON value to sort by, e.g. user.id or items.0"))
+
+        .arg(Arg::new("json-invalid")
+            .long("json-invalid")
+            .num_args(1)
+            .value_name("WHEN")
+            .value_parser(["empty", "error"])
+            .default_value("empty")
+            .help("Treat lines that are not valid JSON as an empty key or as an error"))
+
+        .arg(Arg::new("output")
+            .short('o')
+            .long("output")
+            .num_args(1)
+            .value_name("FILE")
+            .help("Write result to FILE instead of standard output, compressed if FILE ends in .gz or .zst"))
+
+        .arg(Arg::new("in-place")
+            .short('I')
+            .long("in-place")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["output", "files0-from", "c", "C"])
+            .help("Sort FILE and replace its contents with the result"))
+
+        .arg(Arg::new("output-separator")
+            .long("output-separator")
+            .num_args(1)
+            .value_name("STR")
+            .conflicts_with("debug")
+            .help("Separate output records with STR and end the output with a single line ending"))
+
+        .arg(Arg::new("dry-run")
+            .long("dry-run")
+            .action(ArgAction::SetTrue)
+            .help("Sort, then report the output target and line count on standard error without writing anything"))
+
+        .arg(Arg::new("help")
+            .long("help")
+            .action(ArgAction::Help)
+            .help("Print help"))
+}
+
+struct Settings {
+    command: Command,
+    command_line: ArgMatches,
+    defaults: Option<ArgMatches>,
+}
+
+impl Settings {
+    fn parse() -> Result<Self, SortError> {
+        let command_line = command().get_matches();
+        let defaults = match env::var_os("SORT_OPTIONS") {
+            Some(defaults) => Some(Settings::parse_defaults(&defaults.to_string_lossy())?),
+            None => None,
+        };
+        Ok(Settings { command: command(), command_line, defaults })
+    }
 
-        if sorted {
-            println!("Lines are sorted.");
-        } else {
-            println!("Lines are not sorted.");
+    fn parse_defaults(defaults: &str) -> Result<ArgMatches, SortError> {
+        let args = iter::once("sort").chain(defaults.split_whitespace()).map(OsString::from);
+        let matches = command().try_get_matches_from(args).map_err(|e| match e.kind() {
+            ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => e.exit(),
+            _ => {
+                let message = e.to_string();
+                let first_line = message.lines().next().unwrap_or_default();
+                SortError::InvalidOption(format!("SORT_OPTIONS: {}", first_line.trim_start_matches("error: ")))
+            }
+        })?;
+        if let Some(file) = matches.get_many::<String>("filename").and_then(|mut files| files.next()) {
+            return Err(SortError::InvalidOption(format!("SORT_OPTIONS: file operands are not allowed: '{}'", file)));
         }
-        return Ok(());
+        Ok(matches)
+    }
+
+    fn explicit(&self, id: &str) -> bool {
+        self.command_line.value_source(id) == Some(ValueSource::CommandLine)
+    }
+
+    fn conflicting(&self, arg: &Arg, id: &str) -> bool {
+        self.command.get_arg_conflicts_with(arg).iter().any(|other| other.get_id() == id)
+    }
+
+    fn source(&self, id: &str) -> &ArgMatches {
+        let Some(defaults) = &self.defaults else {
+            return &self.command_line;
+        };
+        let Some(arg) = self.command.get_arguments().find(|arg| arg.get_id() == id) else {
+            return &self.command_line;
+        };
+        let overridden = self.command.get_arguments().any(|other| {
+            let other_id = other.get_id().as_str();
+            self.explicit(other_id) && (other_id == id || self.conflicting(other, id) || self.conflicting(arg, other_id))
+        });
+        if overridden { &self.command_line } else { defaults }
+    }
+
+    fn get_flag(&self, id: &str) -> bool {
+        self.source(id).get_flag(id)
     }
 
-    if numeric {
-        sort_by_numeric(&mut lines, column);
+    fn get_one<T: Any + Clone + Send + Sync + 'static>(&self, id: &str) -> Option<&T> {
+        self.source(id).get_one(id)
+    }
+
+    fn get_many<T: Any + Clone + Send + Sync + 'static>(&self, id: &str) -> Option<ValuesRef<'_, T>> {
+        self.source(id).get_many(id)
+    }
+
+    fn contains_id(&self, id: &str) -> bool {
+        self.source(id).contains_id(id)
+    }
+
+    fn value_source(&self, id: &str) -> Option<ValueSource> {
+        self.source(id).value_source(id)
+    }
+}
+
+fn run() -> Result<i32, SortError> {
+    let matches = Settings::parse()?;
+
+    let reverse = matches.get_flag("r");
+    let unique = matches.get_flag("u");
+    let sort_word = matches.get_one::<String>("sort").map(String::as_str);
+    let numeric = matches.get_flag("n") || sort_word == Some("numeric");
+    let month = matches.get_flag("M") || sort_word == Some("month");
+    let suffix = matches.get_flag("h") || sort_word == Some("human-numeric");
+    let general_numeric = matches.get_flag("g") || sort_word == Some("general-numeric");
+    let version = matches.get_flag("V") || sort_word == Some("version");
+    let ignore_case = matches.get_flag("f");
+    let stable = matches.get_flag("s");
+    let ignore_nonprinting = matches.get_flag("i");
+    let dictionary_order = matches.get_flag("d");
+    let collate = matches.get_flag("collate");
+    if collate && !cfg!(feature = "collation") {
+        return Err(SortError::InvalidOption("--collate requires sort to be built with the collation feature".to_string()));
+    }
+    let zero_terminated = matches.get_flag("z");
+    let merge = matches.get_flag("m");
+    let check_order = !matches.get_flag("no-check-order");
+    let random = matches.get_flag("R") || sort_word == Some("random");
+    let auto = matches.get_flag("auto");
+    let debug = matches.get_flag("debug");
+    let progress = matches.get_flag("progress");
+    let random_source = matches.get_one::<String>("random-source").map(String::as_str);
+    let threads = match matches.get_one::<String>("parallel") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(0) => thread::available_parallelism().map_or(1, |n| n.get()),
+            Ok(n) => n,
+            Err(_) => {
+                return Err(SortError::InvalidOption(format!("invalid number of threads '{}'", value)));
+            }
+        },
+        None => 1,
+    };
+    if threads > 1 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(io::Error::other)?;
+    }
+    let buffer_size = match matches.get_one::<String>("S") {
+        Some(value) => parse_buffer_size(value)?,
+        None => DEFAULT_BUFFER_SIZE,
+    };
+    let max_line_bytes = match matches.get_one::<String>("max-line-bytes") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(limit) if limit > 0 => limit,
+            _ => {
+                return Err(SortError::InvalidOption(format!("invalid --max-line-bytes argument '{}'", value)));
+            }
+        },
+        None => usize::MAX,
+    };
+    let batch_size = match matches.get_one::<String>("batch-size") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(size) if size >= 2 => size,
+            _ => {
+                return Err(SortError::InvalidOption(format!("invalid --batch-size argument '{}'", value)));
+            }
+        },
+        None => DEFAULT_BATCH_SIZE,
+    };
+    let compress_program = matches.get_one::<String>("compress-program").map(String::as_str);
+    let delimiter = if zero_terminated { b'\0' } else { b'\n' };
+    let check_quiet = matches.get_flag("C")
+        || matches.get_one::<String>("c").is_some_and(|value| value != "diagnose-first");
+    let check = check_quiet || matches.contains_id("c");
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if check {
+        let ignored = [
+            ("output", "-o"),
+            ("count", "--count"),
+            ("sum", "--sum"),
+            ("output-separator", "--output-separator"),
+            ("dry-run", "--dry-run"),
+            ("debug", "--debug"),
+        ];
+        for (id, name) in ignored {
+            if given(id) {
+                eprintln!("sort: warning: {} has no effect when checking order", name);
+            }
+        }
+    }
+    if given("random-source") && !random {
+        eprintln!("sort: warning: --random-source has no effect without -R");
+    }
+    if given("color") && !debug {
+        eprintln!("sort: warning: --color has no effect without --debug");
+    }
+    let ignore_spaces = matches.get_flag("b");
+    let radix = match matches.get_one::<String>("radix") {
+        Some(value) => match value.parse::<u32>() {
+            Ok(radix @ 2..=36) => Some(radix),
+            _ => {
+                return Err(SortError::InvalidOption(format!("invalid radix '{}'", value)));
+            }
+        },
+        None => None,
+    };
+    let mode = if numeric {
+        SortMode::Numeric
     } else if month {
-        sort_by_month(&mut lines, column);
+        SortMode::Month
     } else if suffix {
-        sort_by_suffix(&mut lines, column);
+        SortMode::Suffix
+    } else if general_numeric {
+        SortMode::GeneralNumeric
+    } else if version {
+        SortMode::Version
+    } else if let Some(radix) = radix {
+        SortMode::Radix(radix)
+    } else if matches.get_flag("L") {
+        match matches.get_one::<String>("length-unit").map(String::as_str) {
+            Some("chars") => SortMode::Length(LengthUnit::Chars),
+            _ => SortMode::Length(LengthUnit::Bytes),
+        }
     } else {
-        sort_by_string(&mut lines, column);
+        SortMode::String
+    };
+
+    let key_width = match matches.get_one::<String>("key-width") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(width) if width > 0 => Some(width),
+            _ => {
+                return Err(SortError::InvalidOption(format!("invalid key width '{}'", value)));
+            }
+        },
+        None => None,
+    };
+    let thousands_sep = match matches.get_one::<String>("thousands-sep") {
+        Some(value) if value.chars().count() == 1 => value.chars().next(),
+        Some(value) => {
+            return Err(SortError::InvalidOption(format!("invalid thousands separator '{}'", value)));
+        }
+        None => None,
+    };
+    let global = KeySpec {
+        start: 1,
+        start_char: 1,
+        end: None,
+        end_char: 0,
+        thousands_sep,
+        currency: matches.get_one::<String>("currency").cloned(),
+        width: key_width,
+        empty_keys: match matches.get_one::<String>("empty-keys").map(String::as_str) {
+            Some("first") => EmptyKeys::First,
+            Some("last") => EmptyKeys::Last,
+            _ => EmptyKeys::Compare,
+        },
+        mode,
+        reverse: false,
+        ignore_case,
+        skip_blanks: ignore_spaces,
+        ignore_nonprinting,
+        dictionary_order,
+        collate,
+    };
+    let key_values: Vec<&String> = matches.get_many::<String>("k").into_iter().flatten().collect();
+    let keys = parse_key_specs(&key_values, &global)?;
+    let separator = match (matches.get_one::<String>("t"), matches.get_one::<String>("field-separator-str")) {
+        (Some(value), _) => Some(parse_separator(value)?.to_string()),
+        (None, Some(value)) if value.is_empty() => {
+            return Err(SortError::InvalidOption("empty field separator".to_string()));
+        }
+        (None, value) => value.cloned(),
+    };
+
+    let in_place = matches.get_flag("in-place");
+    let dry_run = matches.get_flag("dry-run");
+    let filenames: Vec<String> = match (matches.get_one::<String>("files0-from"), matches.get_many::<String>("filename")) {
+        (Some(list), _) => read_file_list(list)?,
+        (None, Some(values)) => values.cloned().collect(),
+        (None, None) => vec!["-".to_string()],
+    };
+    if in_place && (filenames.len() != 1 || filenames[0] == "-") {
+        return Err(SortError::InvalidOption("--in-place requires a single input file".to_string()));
+    }
+    let json_field = matches.get_one::<String>("field").map(|path| path.split('.').map(str::to_string).collect::<Vec<_>>());
+    if json_field.is_some() && !cfg!(feature = "json") {
+        return Err(SortError::InvalidOption("--json requires sort to be built with the json feature".to_string()));
+    }
+    let json_strict = matches.get_one::<String>("json-invalid").is_some_and(|value| value == "error");
+    let mut opts = SortOptions {
+        global,
+        keys,
+        separator,
+        reverse,
+        stable,
+        unique: unique || matches.get_flag("count"),
+        parallel: threads > 1,
+        strip_cr: !zero_terminated,
+        json_field,
+    };
+    if debug && reverse {
+        for (index, key) in opts.keys.iter().enumerate() {
+            if key.reverse {
+                eprintln!("sort: key {} is reversed by both -r and its r modifier, so it sorts ascending", index + 1);
+            }
+        }
     }
 
-    if unique {
-        lines.dedup();
+    let spill_limit = if check || merge || random { usize::MAX } else { buffer_size };
+    let spill_config = SpillConfig {
+        directory: matches.get_one::<String>("T").map_or_else(env::temp_dir, PathBuf::from),
+        next_index: Cell::new(0),
+        delimiter,
+        compress_program,
+    };
+    let input_bytes: u64 = filenames
+        .iter()
+        .filter(|filename| *filename != "-")
+        .filter_map(|filename| fs::metadata(filename).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    let will_spill = if merge {
+        !check && filenames.len() > batch_size
+    } else {
+        spill_limit != usize::MAX && input_bytes > spill_limit as u64
+    };
+    if will_spill || (spill_limit != usize::MAX && matches.contains_id("T")) {
+        check_temp_directory(&spill_config.directory)?;
+    }
+    let mut runs: Vec<TempFile> = Vec::new();
+    let mut chunk: Vec<String> = Vec::new();
+    let mut chunk_bytes = 0;
+    let mut sources: Vec<(&str, usize)> = Vec::new();
+    let mut line_count = 0;
+    let mut crlf_count = 0;
+    let mut has_bom = false;
+    let mut auto_sample: Option<Vec<String>> = auto.then(Vec::new);
+    let streaming = merge && !check;
+    let mut merge_sources = Vec::new();
+    for (index, filename) in filenames.iter().enumerate().filter(|_| streaming) {
+        let mut reader = RecordReader::new(open_input(filename)?, filename, delimiter);
+        reader.max_line_bytes = max_line_bytes;
+        reader.strip_bom = true;
+        let mut head = Vec::new();
+        while head.len() < usize::from(index == 0)
+            || auto_sample.as_ref().is_some_and(|sample| sample.len() < AUTO_SAMPLE_LINES)
+        {
+            let Some(line) = reader.next_record()? else {
+                break;
+            };
+            if let Some(sample) = auto_sample.as_mut() {
+                sample.push(line.clone());
+            }
+            head.push(line);
+        }
+        if index == 0 {
+            has_bom = reader.has_bom;
+        }
+        merge_sources.push((filename.as_str(), head.into_iter().map(Ok).chain(reader)));
+        if merge_sources.len() == batch_size && index + 1 < filenames.len() {
+            if let Some(sample) = auto_sample.take() {
+                apply_auto_mode(&mut opts, &sample, &key_values)?;
+            }
+            let batch = merge_inputs(std::mem::take(&mut merge_sources), check_order.then_some(&opts), json_strict);
+            runs.push(write_run(MergeIter::new(batch, &opts)?, &spill_config)?);
+        }
+    }
+    for filename in filenames.iter().filter(|_| !streaming) {
+        let mut reader = RecordReader::new(open_input(filename)?, filename, delimiter);
+        reader.max_line_bytes = max_line_bytes;
+        reader.strip_bom = true;
+        let first_line = line_count;
+        while let Some(line) = reader.next_record()? {
+            if json_strict && !valid_json(&line) {
+                return Err(SortError::Parse(format!("{}:{}: invalid JSON", filename, line_count - first_line + 1)));
+            }
+            if let Some(sample) = auto_sample.as_mut() {
+                sample.push(line.clone());
+                if sample.len() == AUTO_SAMPLE_LINES {
+                    apply_auto_mode(&mut opts, sample, &key_values)?;
+                    auto_sample = None;
+                }
+            }
+            chunk_bytes += line.len();
+            chunk.push(line);
+            line_count += 1;
+            if progress && line_count % PROGRESS_INTERVAL == 0 {
+                eprintln!("sort: read {} lines", line_count);
+            }
+            if chunk_bytes > spill_limit && auto_sample.is_none() {
+                runs.push(spill_run(&mut chunk, &opts, &spill_config)?);
+                chunk_bytes = 0;
+                if progress {
+                    eprintln!("sort: spilled run {} after {} lines", runs.len(), line_count);
+                }
+            }
+        }
+        crlf_count += reader.crlf_count;
+        if sources.is_empty() {
+            has_bom = reader.has_bom;
+        }
+        sources.push((filename, line_count));
+    }
+    if let Some(sample) = auto_sample {
+        apply_auto_mode(&mut opts, &sample, &key_values)?;
+    }
+    if matches.get_flag("sum") && !check && !opts.keys.first().unwrap_or(&opts.global).mode.is_numeric() {
+        return Err(SortError::InvalidOption("--sum requires a numeric sort: -n, -g, -h or --radix".to_string()));
     }
+    let line_ending = if zero_terminated {
+        "\0"
+    } else if crlf_count * 2 > line_count {
+        "\r\n"
+    } else {
+        "\n"
+    };
 
-    if reverse {
-        lines.reverse();
+    if check {
+        let disorder = find_disorder(&chunk, &opts);
+        if check_quiet {
+            return Ok(if disorder.is_none() { 0 } else { 1 });
+        }
+        return match disorder {
+            Some(index) => {
+                let (name, line_number) = locate_line(&sources, index);
+                let duplicate = compare_lines(&chunk[index - 1], &chunk[index], &opts) == Ordering::Equal;
+                let problem = if duplicate { "duplicate" } else { "disorder" };
+                eprintln!("sort: {}:{}: {}: {}", name, line_number, problem, chunk[index]);
+                Ok(1)
+            }
+            None => {
+                println!("Lines are sorted.");
+                Ok(0)
+            }
+        };
     }
 
-    let sorted_filename = "sorted_".to_string() + &filename;
-    File::create(sorted_filename)?.write_all(&lines.join("\n").as_bytes())?;
-    Ok(())
+    let output_path = if in_place {
+        filenames.first()
+    } else {
+        matches.get_one::<String>("output")
+    };
+    let color = match matches.get_one::<String>("color").map(String::as_str) {
+        Some("always") => true,
+        Some("auto") => {
+            output_path.is_none()
+                && io::stdout().is_terminal()
+                && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        }
+        _ => false,
+    };
+    let temp_output = output_path
+        .filter(|_| !dry_run)
+        .and_then(|path| AtomicOutput::new(path));
+    let sink: Box<dyn OutputSink> = match output_path {
+        _ if dry_run => Box::new(io::sink()),
+        Some(path) => {
+            let target = temp_output.as_ref().map_or(Path::new(path), |atomic| atomic.temp.path.as_path());
+            let file = File::create(target).map_err(|e| {
+                io::Error::new(e.kind(), format!("open failed: {}: {}", path, describe_io_error(&e)))
+            })?;
+            compress_output(path, file)?
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut output = io::BufWriter::new(sink);
+    if has_bom {
+        output.write_all("\u{feff}".as_bytes())?;
+    }
+    let format = OutputFormat {
+        line_ending,
+        separator: matches.get_one::<String>("output-separator").map(String::as_str),
+        unique,
+        count: matches.get_flag("count"),
+        debug,
+        color,
+        sum: matches.get_flag("sum"),
+        progress,
+    };
+    if merge && !runs.is_empty() && !merge_sources.is_empty() {
+        let batch = merge_inputs(merge_sources, check_order.then_some(&opts), json_strict);
+        runs.push(write_run(MergeIter::new(batch, &opts)?, &spill_config)?);
+        merge_sources = Vec::new();
+    }
+    let written = if merge && runs.is_empty() {
+        let sources = merge_inputs(merge_sources, check_order.then_some(&opts), json_strict);
+        write_records(&mut output, MergeIter::new(sources, &opts)?, &format, &opts)?
+    } else if random {
+        shuffle_lines(&mut chunk, &opts, random_seed(random_source)?);
+        write_records(&mut output, chunk.into_iter().map(Ok), &format, &opts)?
+    } else if runs.is_empty() {
+        sort_in_place(&mut chunk, &opts);
+        write_records(&mut output, chunk.into_iter().map(Ok), &format, &opts)?
+    } else {
+        if !chunk.is_empty() {
+            runs.push(spill_run(&mut chunk, &opts, &spill_config)?);
+        }
+        while runs.len() > batch_size {
+            if progress {
+                eprintln!("sort: merging {} runs in batches of {}", runs.len(), batch_size);
+            }
+            runs = merge_runs(runs, &opts, &spill_config, batch_size)?;
+        }
+        if progress {
+            eprintln!("sort: merging {} runs", runs.len());
+        }
+        let readers = runs.iter().map(|run| open_run(run, &spill_config)).collect::<io::Result<Vec<_>>>()?;
+        write_records(&mut output, MergeIter::new(readers, &opts)?, &format, &opts)?
+    };
+    output.into_inner().map_err(io::IntoInnerError::into_error)?.finish()?;
+    if dry_run {
+        let target = output_path.map_or("standard output", String::as_str);
+        eprintln!("sort: dry run: would write {} lines to {}", written, target);
+        return Ok(0);
+    }
+    if let Some(atomic) = &temp_output {
+        atomic.commit()?;
+    }
+    Ok(0)
 }
 
+fn main() {
+    match run() {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("sort: {}", e);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,1109 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{self, Command, Output, Stdio};
+
+fn sort_with(args: &[&str], input: &[u8], envs: &[(&str, &str)]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sort"))
+        .args(args)
+        .env_remove("SORT_OPTIONS")
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("sort binary should start");
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let _ = stdin.write_all(input);
+    drop(stdin);
+    child.wait_with_output().expect("sort binary should finish")
+}
+
+fn sort(args: &[&str], input: &str) -> Output {
+    sort_with(args, input.as_bytes(), &[])
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("sort-test-{}-{}", process::id(), name));
+    fs::write(&path, contents).expect("temporary file should be writable");
+    path
+}
+
+#[test]
+fn reads_standard_input_without_a_filename() {
+    let output = sort(&[], "b\nc\na\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "a\nb\nc\n");
+}
+
+#[test]
+fn reads_standard_input_for_dash() {
+    let output = sort(&["-n", "-"], "10\n9\n");
+    assert_eq!(stdout(&output), "9\n10\n");
+}
+
+#[test]
+fn writes_to_standard_output_with_a_trailing_newline() {
+    let output = sort(&[], "b\na");
+    assert_eq!(stdout(&output), "a\nb\n");
+}
+
+#[test]
+fn writes_to_the_output_file_instead_of_standard_output() {
+    let input = temp_file("output-input", "10\n9\n");
+    let target = temp_file("output-target", "");
+    let output = sort(&["-n", input.to_str().unwrap(), "-o", target.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert_eq!(fs::read_to_string(&target).unwrap(), "9\n10\n");
+}
+
+#[test]
+fn output_file_may_be_the_input_file() {
+    let path = temp_file("output-same", "b\na\n");
+    let output = sort(&[path.to_str().unwrap(), "-o", path.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\n");
+}
+
+#[test]
+fn sorts_several_files_and_standard_input_as_one_stream() {
+    let first = temp_file("concat-first", "c\na\n");
+    let second = temp_file("concat-second", "d\n");
+    let output = sort(&[first.to_str().unwrap(), "-", second.to_str().unwrap()], "b\n");
+    assert_eq!(stdout(&output), "a\nb\nc\nd\n");
+}
+
+#[test]
+fn names_the_missing_file_among_several() {
+    let present = temp_file("concat-present", "a\n");
+    let output = sort(&[present.to_str().unwrap(), "missing-input.txt"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("missing-input.txt"));
+}
+
+#[test]
+fn reports_a_missing_file_like_gnu_sort() {
+    let output = sort(&["no-such-file.txt"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "sort: cannot open 'no-such-file.txt': No such file or directory\n");
+}
+
+#[test]
+fn replaces_invalid_utf8_instead_of_panicking() {
+    let output = sort_with(&[], b"b\n\xffa\na\n", &[]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "a\nb\n\u{fffd}a\n");
+    assert!(stderr(&output).contains("invalid UTF-8"));
+}
+
+#[test]
+fn numeric_sort_survives_nan_and_inf() {
+    let output = sort(&["-n"], "3\nnan\ninf\n-inf\n1\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "-inf\ninf\nnan\n1\n3\n");
+}
+
+#[test]
+fn unique_compares_the_key_in_string_mode() {
+    let output = sort(&["-k2", "-u"], "a x\nb y\nc x\n");
+    assert_eq!(stdout(&output), "a x\nb y\n");
+}
+
+#[test]
+fn unique_compares_the_key_in_numeric_mode() {
+    let output = sort(&["-n", "-u"], "1 a\n1.0 b\n2 c\n");
+    assert_eq!(stdout(&output), "1 a\n2 c\n");
+}
+
+#[test]
+fn reverse_unique_keeps_one_of_each_in_descending_order() {
+    let output = sort(&["-ru"], "b\na\nb\nc\na\n");
+    assert_eq!(stdout(&output), "c\nb\na\n");
+}
+
+#[test]
+fn general_numeric_sort_orders_exponents_and_negatives() {
+    let output = sort(&["-g"], "1.5e10\n-3.2E-4\n+2\n 7\n-1e3\n");
+    assert_eq!(stdout(&output), "-1e3\n-3.2E-4\n+2\n 7\n1.5e10\n");
+}
+
+#[test]
+fn version_sort_compares_numeric_runs_by_value() {
+    let output = sort(&["-V"], "file10\nfile2\nfile1\nv1.2.10\nv1.2.9\nf8\nf08\n");
+    assert_eq!(stdout(&output), "f08\nf8\nfile1\nfile2\nfile10\nv1.2.9\nv1.2.10\n");
+}
+
+#[test]
+fn ignore_case_with_stable_keeps_input_order_of_folded_ties() {
+    let output = sort(&["-f", "-s"], "b\nApple\napple\nAPPLE\nB\n");
+    assert_eq!(stdout(&output), "Apple\napple\nAPPLE\nb\nB\n");
+}
+
+#[test]
+fn ignore_case_composes_with_keys_reverse_and_unique() {
+    assert_eq!(stdout(&sort(&["-f", "-k2", "-u"], "x b\ny B\nz a\n")), "z a\nx b\n");
+    assert_eq!(stdout(&sort(&["-f", "-r", "-s"], "b\nB\na\n")), "b\nB\na\n");
+}
+
+#[test]
+fn stable_keeps_input_order_of_equal_keys() {
+    let input = "x 1 c\nx 1 a\nx 1 b\n";
+    assert_eq!(stdout(&sort(&["-k2,2", "-s"], input)), input);
+    assert_eq!(stdout(&sort(&["-k2,2"], input)), "x 1 a\nx 1 b\nx 1 c\n");
+}
+
+#[test]
+fn field_separator_preserves_empty_comma_fields() {
+    let output = sort(&["-t,", "-k2"], "c,b,x\nb,a,\na,,c\n");
+    assert_eq!(stdout(&output), "a,,c\nb,a,\nc,b,x\n");
+}
+
+#[test]
+fn field_separator_accepts_a_tab() {
+    let output = sort(&["-t", "\t", "-k2"], "c\tb\tx\nb\ta\t\na\t\tc\n");
+    assert_eq!(stdout(&output), "a\t\tc\nb\ta\t\nc\tb\tx\n");
+}
+
+#[test]
+fn key_range_spans_several_fields() {
+    let output = sort(&["-k2,3"], "x b c\ny a d\nz b a\n");
+    assert_eq!(stdout(&output), "y a d\nz b a\nx b c\n");
+}
+
+#[test]
+fn key_range_rejects_an_end_before_the_start() {
+    let output = sort(&["-k3,2"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("invalid key range '3,2'"));
+}
+
+#[test]
+fn second_key_breaks_ties_in_the_first() {
+    let output = sort(&["-k1,1", "-k2,2n"], "a 10\nb 1\na 9\n");
+    assert_eq!(stdout(&output), "a 9\na 10\nb 1\n");
+}
+
+#[test]
+fn key_modifiers_apply_to_their_own_key() {
+    let output = sort(&["-k2n", "-k1"], "c 10\nb 10\na 9\n");
+    assert_eq!(stdout(&output), "a 9\nb 10\nc 10\n");
+}
+
+#[test]
+fn quiet_check_only_sets_the_exit_status() {
+    let sorted = sort(&["-C"], "a\nb\n");
+    assert_eq!(sorted.status.code(), Some(0));
+    let unsorted = sort(&["-C"], "b\na\n");
+    assert_eq!(unsorted.status.code(), Some(1));
+    assert!(unsorted.stdout.is_empty() && unsorted.stderr.is_empty());
+}
+
+#[test]
+fn check_reports_the_first_disordered_line() {
+    let output = sort(&["-c"], "a\nc\nb\nd\n");
+    assert_eq!(output.status.code(), Some(1));
+    let message = stderr(&output);
+    let (location, line) = message.trim_end().rsplit_once(": disorder: ").unwrap();
+    assert_eq!(location, "sort: -:3");
+    assert_eq!(line, "b");
+}
+
+#[test]
+fn check_accepts_plain_sorted_text() {
+    let output = sort(&["-c"], "apple\nbanana\ncherry\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn ignore_nonprinting_compares_without_control_characters() {
+    let output = sort(&["-i"], "b\n\x01a\n\tc\n");
+    assert_eq!(stdout(&output), "\x01a\nb\n\tc\n");
+}
+
+#[test]
+fn dictionary_order_ignores_punctuation() {
+    let output = sort(&["-d"], "cop\nco-op\ncoo\n");
+    assert_eq!(stdout(&output), "coo\nco-op\ncop\n");
+}
+
+#[test]
+fn dictionary_order_composes_with_ignore_case() {
+    let output = sort(&["-d", "-f"], "COP\ncoop\nCo-op\n");
+    assert_eq!(stdout(&output), "Co-op\ncoop\nCOP\n");
+}
+
+#[test]
+fn ignore_leading_blanks_keeps_the_indentation_in_output() {
+    let output = sort(&["-b"], "  b\na\n    c\n");
+    assert_eq!(stdout(&output), "a\n  b\n    c\n");
+}
+
+#[test]
+fn month_sort_ignores_case_and_rejects_near_misses() {
+    let output = sort(&["-M"], "feb\nJane\njan\nMAR\nxyz\n");
+    assert_eq!(stdout(&output), "Jane\nxyz\njan\nfeb\nMAR\n");
+}
+
+#[test]
+fn human_sort_orders_decimal_and_binary_units() {
+    let output = sort(&["-h"], "1M\n1Ki\n1K\n1024\n");
+    assert_eq!(stdout(&output), "1K\n1024\n1Ki\n1M\n");
+}
+
+#[test]
+fn human_sort_handles_negative_values() {
+    let output = sort(&["-h"], "0\n-2K\n1\n-1\n");
+    assert_eq!(stdout(&output), "-2K\n-1\n0\n1\n");
+}
+
+#[test]
+fn numeric_sort_places_non_numbers_before_every_number() {
+    let output = sort(&["-n"], "5\nabc\n-1.7976931348623157e308\n0.001\n");
+    assert_eq!(stdout(&output), "abc\n-1.7976931348623157e308\n0.001\n5\n");
+}
+
+#[test]
+fn help_lists_the_main_options() {
+    let output = sort(&["--help"], "");
+    assert!(output.status.success());
+    let help = stdout(&output);
+    for option in ["-k <KEYDEF>", "-n", "-r", "-u", "--field-separator"] {
+        assert!(help.contains(option), "help should mention {option}");
+    }
+}
+
+#[test]
+fn zero_terminated_round_trips_nul_separators() {
+    let output = sort_with(&["-z"], b"b\0a\nx\0c\0", &[]);
+    assert_eq!(output.stdout, b"a\nx\0b\0c\0");
+}
+
+#[test]
+fn preserves_crlf_line_endings() {
+    let output = sort(&[], "b\r\na\r\nc\r\n");
+    assert_eq!(stdout(&output), "a\r\nb\r\nc\r\n");
+}
+
+#[test]
+fn ignores_a_trailing_carriage_return_in_keys() {
+    let output = sort(&["-n"], "10\r\n9\r\n");
+    assert_eq!(stdout(&output), "9\r\n10\r\n");
+}
+
+#[test]
+fn output_file_ends_with_a_newline() {
+    let input = temp_file("newline-input", "b\na");
+    let target = env::temp_dir().join(format!("sort-test-{}-newline-target", process::id()));
+    sort(&[input.to_str().unwrap(), "-o", target.to_str().unwrap()], "");
+    assert_eq!(fs::read(&target).unwrap().last(), Some(&b'\n'));
+}
+
+#[test]
+fn merges_pre_sorted_files_numerically() {
+    let first = temp_file("merge-first", "1\n5\n10\n");
+    let second = temp_file("merge-second", "2\n9\n100\n");
+    let output = sort(&["-m", "-n", first.to_str().unwrap(), second.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1\n2\n5\n9\n10\n100\n");
+}
+
+#[test]
+fn spills_runs_when_the_buffer_is_small() {
+    let directory = env::temp_dir().join(format!("sort-test-{}-spill", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let input: String = (1..=200).rev().map(|n| format!("{n}\n")).collect();
+    let expected: String = (1..=200).map(|n| format!("{n}\n")).collect();
+    let output = sort(&["-n", "-S", "100b", "-T", directory.to_str().unwrap()], &input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), expected);
+    assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+}
+
+#[test]
+fn parallel_sort_matches_the_sequential_output() {
+    let input: String = (0..5000).map(|n| format!("{}\n", (n * 7919) % 5003)).collect();
+    let sequential = sort(&["-n"], &input);
+    let parallel = sort(&["-n", "--parallel=4"], &input);
+    assert_eq!(stdout(&parallel), stdout(&sequential));
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn decompresses_gzip_input() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"10\n9\n100\n").unwrap();
+    let path = env::temp_dir().join(format!("sort-test-{}-input.gz", process::id()));
+    fs::write(&path, encoder.finish().unwrap()).unwrap();
+    let output = sort(&["-n", path.to_str().unwrap()], "");
+    assert_eq!(stdout(&output), "9\n10\n100\n");
+}
+
+#[test]
+fn random_sort_is_reproducible_with_a_random_source() {
+    let seed = temp_file("random-seed", "seed\n");
+    let args = ["-R", "--random-source", seed.to_str().unwrap()];
+    let output = sort(&args, "a\nb\nc\nd\ne\na\n");
+    assert_eq!(stdout(&output), "a\na\nc\nd\ne\nb\n");
+    assert_eq!(stdout(&sort(&args, "a\nb\nc\nd\ne\na\n")), stdout(&output));
+}
+
+#[test]
+fn debug_marks_the_selected_column() {
+    let output = sort(&["--debug", "-k2,2"], "x abc y\n");
+    assert_eq!(stdout(&output), "x abc y\n ____\n");
+}
+
+#[test]
+fn files0_from_reads_every_listed_file() {
+    let first = temp_file("files0-first", "b\na\n");
+    let second = temp_file("files0-second", "c\n");
+    let list = temp_file("files0-list", &format!("{}\0{}\0", first.display(), second.display()));
+    let output = sort(&["--files0-from", list.to_str().unwrap()], "");
+    assert_eq!(stdout(&output), "a\nb\nc\n");
+}
+
+#[test]
+fn cached_numeric_keys_match_the_uncached_comparison() {
+    let input: String = (0..500).map(|n| format!("{}.{} x{}\n", (n * 37) % 101, n % 7, n % 3)).collect();
+    let cached = sort(&["-n"], &input);
+    let uncached = sort(&["-k1n", "-k1n"], &input);
+    assert_eq!(stdout(&cached), stdout(&uncached));
+}
+
+#[test]
+fn rejects_a_zero_field_number() {
+    let output = sort(&["-k0"], "a\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("field number is zero"));
+}
+
+#[test]
+fn out_of_range_field_is_an_empty_key() {
+    let output = sort(&["-k5"], "c b a\nb\na a a\n");
+    assert_eq!(stdout(&output), "a a a\nb\nc b a\n");
+}
+
+#[test]
+fn short_lines_sort_by_an_empty_missing_field() {
+    let output = sort(&["-k3"], "c b a\nz z b\nb\na a z\n");
+    assert_eq!(stdout(&output), "b\nc b a\nz z b\na a z\n");
+}
+
+#[test]
+fn failed_sort_leaves_the_output_file_untouched() {
+    let directory = env::temp_dir().join(format!("sort-test-{}-atomic", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let target = directory.join("target");
+    fs::write(&target, "original\n").unwrap();
+    let input = temp_file("atomic-input", "b\na\n");
+    let output = sort(&[input.to_str().unwrap(), "missing-input.txt", "-o", target.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(fs::read_to_string(&target).unwrap(), "original\n");
+    assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn output_through_a_symlink_replaces_the_target() {
+    use std::os::unix::fs::symlink;
+
+    let directory = env::temp_dir().join(format!("sort-test-{}-symlink-output", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let (target, link) = (directory.join("target"), directory.join("link"));
+    fs::write(&target, "original\n").unwrap();
+    let _ = fs::remove_file(&link);
+    symlink(&target, &link).unwrap();
+    let output = sort(&["-o", link.to_str().unwrap()], "b\na\n");
+    assert!(output.status.success());
+    assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&target).unwrap(), "a\nb\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn output_keeps_hard_links_and_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = env::temp_dir().join(format!("sort-test-{}-hard-link-output", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let (target, other) = (directory.join("target"), directory.join("other"));
+    fs::write(&target, "original\n").unwrap();
+    fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+    let _ = fs::remove_file(&other);
+    fs::hard_link(&target, &other).unwrap();
+    let output = sort(&["-o", target.to_str().unwrap()], "b\na\n");
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&other).unwrap(), "a\nb\n");
+    fs::remove_file(&other).unwrap();
+    sort(&["-o", target.to_str().unwrap()], "d\nc\n");
+    assert_eq!(fs::read_to_string(&target).unwrap(), "c\nd\n");
+    assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o640);
+}
+
+#[test]
+fn check_follows_human_order_in_both_directions() {
+    assert_eq!(sort(&["-c", "-h"], "1K\n2K\n1M\n").status.code(), Some(0));
+    assert_eq!(sort(&["-c", "-h", "-r"], "1M\n2K\n1K\n").status.code(), Some(0));
+    assert_eq!(sort(&["-c", "-h"], "1M\n2K\n1K\n").status.code(), Some(1));
+}
+
+#[test]
+fn numeric_check_accepts_numeric_sort_output() {
+    let sorted = sort(&["-n"], "3\nabc\n1.5\n\n-2\nx\n");
+    let check = sort_with(&["-n", "-c"], &sorted.stdout, &[]);
+    assert_eq!(check.status.code(), Some(0));
+}
+
+#[test]
+fn auto_sorts_an_all_numeric_column_numerically() {
+    let output = sort(&["--auto"], "10\n9\n100\n");
+    assert_eq!(stdout(&output), "9\n10\n100\n");
+}
+
+#[test]
+fn auto_sorts_a_mixed_column_as_text() {
+    let output = sort(&["--auto"], "10\n9\nabc\n");
+    assert_eq!(stdout(&output), "10\n9\nabc\n");
+}
+
+#[test]
+fn auto_detects_numbers_the_way_numeric_sort_parses_them() {
+    for odd in ["inf", "nan", "1e5"] {
+        let output = sort(&["--auto"], &format!("10\n{odd}\n9\n"));
+        let mut expected = ["10", odd, "9"];
+        expected.sort();
+        assert_eq!(stdout(&output), format!("{}\n", expected.join("\n")), "{odd} should make --auto sort text");
+    }
+    let output = sort(&["--auto", "--thousands-sep", ","], "1,000\n999\n");
+    assert_eq!(stdout(&output), "999\n1,000\n");
+}
+
+#[test]
+fn key_character_offsets_slice_the_field() {
+    let output = sort(&["-k1.2,1.4"], "xabcd\nxzaa\nyabb\n");
+    assert_eq!(stdout(&output), "yabb\nxabcd\nxzaa\n");
+}
+
+#[test]
+fn in_place_replaces_the_file_with_its_sorted_contents() {
+    let path = temp_file("in-place", "c\na\nb\n");
+    let output = sort(&["-I", path.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\nc\n");
+}
+
+#[test]
+fn in_place_refuses_standard_input() {
+    let output = sort(&["-I", "-"], "b\na\n");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[cfg(unix)]
+#[test]
+fn in_place_through_a_symlink_sorts_the_target() {
+    use std::os::unix::fs::symlink;
+
+    let directory = env::temp_dir().join(format!("sort-test-{}-symlink-in-place", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let (target, link) = (directory.join("target"), directory.join("link"));
+    fs::write(&target, "c\na\nb\n").unwrap();
+    let _ = fs::remove_file(&link);
+    symlink(&target, &link).unwrap();
+    let output = sort(&["-I", link.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&target).unwrap(), "a\nb\nc\n");
+}
+
+#[test]
+fn rejects_a_backwards_key_range() {
+    let output = sort(&["-k3,1"], "a\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "sort: invalid key range '3,1': end precedes start\n");
+}
+
+#[test]
+fn thousands_separator_is_ignored_in_numeric_keys() {
+    assert_eq!(stdout(&sort(&["-n", "--thousands-sep", ","], "1,000\n999\n")), "999\n1,000\n");
+    let output = sort(&["-t,", "-k2n", "--thousands-sep", ","], "x,1,000\ny,999\n");
+    assert_eq!(stdout(&output), "y,999\nx,1,000\n");
+}
+
+#[test]
+fn sort_word_numeric_matches_the_short_flag() {
+    let input = "10\n9\n-1\nabc\n100\n";
+    assert_eq!(stdout(&sort(&["--sort=numeric"], input)), stdout(&sort(&["-n"], input)));
+}
+
+#[test]
+fn unique_collapses_duplicates_scattered_through_the_input() {
+    let output = sort(&["-n", "-u"], "3\n1\n2\n3\n1\n2\n1\n");
+    assert_eq!(stdout(&output), "1\n2\n3\n");
+}
+
+#[test]
+fn random_unique_collapses_numerically_equal_keys() {
+    let output = sort(&["-R", "-k1n", "-u"], "3\n1\n03\n2.0\n1.0\n2\n01\n");
+    let mut printed: Vec<String> = stdout(&output).lines().map(String::from).collect();
+    printed.sort();
+    assert_eq!(printed, ["1", "2.0", "3"]);
+}
+
+#[test]
+fn check_exit_status_reflects_the_order() {
+    assert_eq!(sort(&["-c"], "a\nb\n").status.code(), Some(0));
+    assert_eq!(sort(&["-c"], "b\na\n").status.code(), Some(1));
+}
+
+#[test]
+fn key_blank_modifier_skips_leading_spaces_in_that_field() {
+    let input = "a:  b\nb:a\nc: c\n";
+    assert_eq!(stdout(&sort(&["-t:", "-k2b"], input)), "b:a\na:  b\nc: c\n");
+    assert_eq!(stdout(&sort(&["-t:", "-k2"], input)), "a:  b\nc: c\nb:a\n");
+}
+
+#[test]
+fn compress_program_round_trips_spilled_runs() {
+    let input: String = (1..=200).rev().map(|n| format!("{n}\n")).collect();
+    let expected: String = (1..=200).map(|n| format!("{n}\n")).collect();
+    let output = sort(&["-n", "-S", "100b", "--compress-program", "gzip"], &input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), expected);
+}
+
+#[test]
+fn byte_order_mark_does_not_join_the_first_key() {
+    let output = sort(&[], "\u{feff}c\nb\na\n");
+    assert_eq!(stdout(&output), "\u{feff}a\nb\nc\n");
+}
+
+#[test]
+fn byte_order_marks_inside_the_input_survive_spilled_runs() {
+    let output = sort(&["-S", "1b"], "b\n\u{feff}a\n\u{feff}z\n");
+    assert_eq!(stdout(&output), "b\n\u{feff}a\n\u{feff}z\n");
+}
+
+#[test]
+fn merge_reports_a_disordered_input() {
+    let unsorted = temp_file("merge-unsorted", "b\na\n");
+    let sorted = temp_file("merge-sorted", "c\nd\n");
+    let output = sort(&["-m", unsorted.to_str().unwrap(), sorted.to_str().unwrap()], "");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains(&format!("{}:2: disorder: a", unsorted.display())));
+}
+
+#[test]
+fn rejects_a_line_over_the_byte_limit() {
+    let output = sort(&["--max-line-bytes", "4"], "abcdef\nab\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "sort: -: line exceeds 4 bytes\n");
+}
+
+#[test]
+fn human_sort_accepts_a_space_before_the_unit() {
+    let output = sort(&["-h"], "12 MB\n12K\n12 K\n13\n");
+    assert_eq!(stdout(&output), "13\n12 K\n12K\n12 MB\n");
+}
+
+#[cfg(feature = "collation")]
+#[test]
+fn collate_sorts_accented_letters_beside_their_base_letters() {
+    let output = sort(&["--collate"], "f\n\u{e9}\ne\nz\n");
+    assert_eq!(stdout(&output), "e\n\u{e9}\nf\nz\n");
+}
+
+#[test]
+fn options_may_follow_the_file_name() {
+    let path = temp_file("flag-order", "10\n9\n100\n");
+    let before = sort(&["-n", path.to_str().unwrap()], "");
+    let after = sort(&[path.to_str().unwrap(), "-n"], "");
+    assert_eq!(stdout(&after), "9\n10\n100\n");
+    assert_eq!(stdout(&before), stdout(&after));
+}
+
+#[test]
+fn sum_appends_the_total_of_the_numeric_keys() {
+    let output = sort(&["-n", "--sum"], "3\n1\n2\n");
+    assert_eq!(stdout(&output), "1\n2\n3\n6\n");
+}
+
+#[test]
+fn sum_prints_decimal_totals_without_float_noise() {
+    assert_eq!(stdout(&sort(&["-n", "--sum"], "0.1\n0.2\n")), "0.1\n0.2\n0.3\n");
+    assert_eq!(stdout(&sort(&["-g", "--sum"], "1e15\n2\n")), "2\n1e15\n1000000000000002\n");
+}
+
+#[test]
+fn sum_requires_a_numeric_sort() {
+    let output = sort(&["--sum"], "1\n2\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "sort: --sum requires a numeric sort: -n, -g, -h or --radix\n");
+}
+
+#[test]
+fn sum_scales_human_keys_and_counts_collapsed_lines() {
+    assert_eq!(stdout(&sort(&["-k3h", "--sum"], "1 x 2K\n2 y 1K\n")), "2 y 1K\n1 x 2K\n3000\n");
+    assert_eq!(stdout(&sort(&["-n", "-u", "--sum"], "5\n5\n1\n")), "1\n5\n11\n");
+}
+
+#[test]
+fn ignore_leading_blanks_keeps_trailing_spaces_in_output() {
+    let output = sort(&["-b"], "b  \na \nc\n");
+    assert_eq!(stdout(&output), "a \nb  \nc\n");
+}
+
+#[test]
+fn radix_sort_compares_hexadecimal_values() {
+    let output = sort(&["--radix", "16"], "0x10\n0xA\n0x2\nff\n");
+    assert_eq!(stdout(&output), "0x2\n0xA\n0x10\nff\n");
+}
+
+#[test]
+fn negative_fields_count_from_the_end_of_each_line() {
+    let input = "x y 3\nz 1\na b c 2\n";
+    assert_eq!(stdout(&sort(&["-k-1"], input)), "z 1\na b c 2\nx y 3\n");
+    assert_eq!(stdout(&sort(&["-k-2,-2"], input)), "a b c 2\nx y 3\nz 1\n");
+}
+
+#[test]
+fn negative_field_beyond_the_start_is_an_empty_key() {
+    let output = sort(&["-k-3,-3"], "b c d\na b\n");
+    assert_eq!(stdout(&output), "a b\nb c d\n");
+}
+
+#[test]
+fn progress_goes_to_standard_error_only() {
+    let input: String = (1..=2000).rev().map(|n| format!("{n}\n")).collect();
+    let expected: String = (1..=2000).map(|n| format!("{n}\n")).collect();
+    let output = sort(&["-n", "-S", "1k", "--progress"], &input);
+    assert_eq!(stdout(&output), expected);
+    assert!(stderr(&output).contains("sort: spilled run 1 after"));
+}
+
+#[test]
+fn month_sort_reads_the_field_as_a_whole_token() {
+    let output = sort(&["-M"], "Marble\nmarch\nMar\nFeb\n");
+    assert_eq!(stdout(&output), "Marble\nFeb\nMar\nmarch\n");
+}
+
+#[test]
+fn long_ignore_leading_blanks_matches_the_short_flag() {
+    let input = "  b\na\n c\n";
+    assert_eq!(stdout(&sort(&["--ignore-leading-blanks"], input)), "a\n  b\n c\n");
+    assert_eq!(stdout(&sort(&["-b"], input)), stdout(&sort(&["--ignore-leading-blanks"], input)));
+}
+
+#[test]
+fn temporary_directory_flag_is_checked_up_front() {
+    let output = sort(&["-T", "/nonexistent-sort-dir"], "b\na\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "sort: cannot create temporary file in '/nonexistent-sort-dir': No such file or directory\n");
+}
+
+#[test]
+fn unwritable_tmpdir_is_reported_before_reading_large_input() {
+    let not_a_directory = temp_file("tmpdir-file", "");
+    let input = temp_file("tmpdir-input", &(1..=200).map(|n| format!("{n}\n")).collect::<String>());
+    let tmpdir = [("TMPDIR", not_a_directory.to_str().unwrap())];
+    let output = sort_with(&["-S", "100b", input.to_str().unwrap(), "missing-input.txt"], b"", &tmpdir);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), format!("sort: cannot create temporary file in '{}': Not a directory\n", not_a_directory.display()));
+}
+
+#[test]
+fn spills_go_to_tmpdir_unless_the_flag_overrides_it() {
+    let input: String = (1..=200).rev().map(|n| format!("{n}\n")).collect();
+    let tmpdir = [("TMPDIR", "/nonexistent-sort-dir")];
+    let spilled = sort_with(&["-n", "-S", "100b"], input.as_bytes(), &tmpdir);
+    assert!(stderr(&spilled).contains("'/nonexistent-sort-dir'"));
+    let in_memory = sort_with(&["-n"], input.as_bytes(), &tmpdir);
+    assert!(in_memory.status.success());
+    let directory = env::temp_dir().join(format!("sort-test-{}-tmpdir", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let overridden = sort_with(&["-n", "-S", "100b", "-T", directory.to_str().unwrap()], input.as_bytes(), &tmpdir);
+    assert!(overridden.status.success());
+}
+
+#[test]
+fn count_prefixes_the_first_line_of_each_group() {
+    let output = sort(&["-k1,1", "--count"], "b 1\na 1\nb 2\nb 3\n");
+    assert_eq!(stdout(&output), "      1 a 1\n      3 b 1\n");
+}
+
+#[test]
+fn rejects_an_enormous_field_number() {
+    let output = sort(&["-k99999999999999999999"], "a\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("sort: invalid field number"));
+}
+
+#[test]
+fn global_and_key_reverse_cancel_out() {
+    let output = sort(&["-r", "-k2r"], "a 2\nb 1\nc 3\n");
+    assert_eq!(stdout(&output), "b 1\na 2\nc 3\n");
+}
+
+#[test]
+fn debug_notes_a_doubly_reversed_key() {
+    let output = sort(&["--debug", "-r", "-k2r"], "a 2\n");
+    assert!(stderr(&output).contains("key 1 is reversed by both -r and its r modifier"));
+}
+
+#[test]
+fn key_width_ignores_characters_past_the_limit() {
+    let input = "abcdefX\nabcdefA\n";
+    assert_eq!(stdout(&sort(&["--key-width", "6", "-s"], input)), input);
+    assert_eq!(stdout(&sort(&["--key-width", "6", "-u"], input)), "abcdefX\n");
+}
+
+#[test]
+fn key_width_counts_characters_not_bytes() {
+    let output = sort(&["--key-width", "2", "-u"], "\u{e9}\u{e9}b\n\u{e9}\u{e9}a\n");
+    assert_eq!(stdout(&output), "\u{e9}\u{e9}b\n");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_sorts_by_a_numeric_field() {
+    let input = "{\"id\":10,\"n\":\"b\"}\n{\"id\":9,\"n\":\"c\"}\n{\"id\":100,\"n\":\"a\"}\nnot json\n";
+    let output = sort(&["--json", "--field", "id", "-n"], input);
+    assert_eq!(stdout(&output), "not json\n{\"id\":9,\"n\":\"c\"}\n{\"id\":10,\"n\":\"b\"}\n{\"id\":100,\"n\":\"a\"}\n");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_sorts_by_a_string_field() {
+    let input = "{\"user\":{\"name\":\"bob\"}}\n{\"user\":{\"name\":\"al\"}}\n";
+    let output = sort(&["--json", "--field", "user.name"], input);
+    assert_eq!(stdout(&output), "{\"user\":{\"name\":\"al\"}}\n{\"user\":{\"name\":\"bob\"}}\n");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_invalid_error_rejects_a_bad_line() {
+    let output = sort(&["--json", "--field", "id", "--json-invalid", "error"], "{\"id\":1}\nbad\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("-:2: invalid JSON"));
+}
+
+#[test]
+fn empty_keys_sort_first_or_last_in_every_mode() {
+    assert_eq!(stdout(&sort(&["--empty-keys", "first"], "a\n\nb\n")), "\na\nb\n");
+    assert_eq!(stdout(&sort(&["--empty-keys", "last"], "a\n\nb\n")), "a\nb\n\n");
+    assert_eq!(stdout(&sort(&["-n", "--empty-keys", "last"], "5\n\n-1\n")), "-1\n5\n\n");
+    assert_eq!(stdout(&sort(&["-r", "--empty-keys", "first"], "a\n\nb\n")), "\nb\na\n");
+    assert_eq!(stdout(&sort(&["-n", "-r", "--empty-keys", "last"], "5\n\n-1\n")), "5\n-1\n\n");
+    assert_eq!(stdout(&sort(&["-k2r", "--empty-keys", "first"], "x a\ny\nz b\n")), "y\nz b\nx a\n");
+    assert_eq!(stdout(&sort(&["-r", "-k2r", "--empty-keys", "last"], "x a\ny\nz b\n")), "x a\nz b\ny\n");
+    assert_eq!(stdout(&sort(&["-M", "--empty-keys", "last"], "feb\n\njan\n")), "jan\nfeb\n\n");
+    assert_eq!(stdout(&sort(&["-h", "--empty-keys", "first"], "2K\n\n1K\n")), "\n1K\n2K\n");
+}
+
+#[test]
+fn fields_start_at_the_blanks_before_each_word() {
+    let output = sort(&["-k2,2"], "  b x\na  y\n c   w\n");
+    assert_eq!(stdout(&output), " c   w\na  y\n  b x\n");
+}
+
+#[test]
+fn leading_blanks_belong_to_the_first_field() {
+    let output = sort(&["-k2,2", "-b"], "  b x\na  y\n c   w\n");
+    assert_eq!(stdout(&output), " c   w\n  b x\na  y\n");
+}
+
+#[test]
+fn batch_size_merges_runs_in_several_passes() {
+    let input: String = (1..=300).rev().map(|n| format!("{n}\n")).collect();
+    let expected: String = (1..=300).map(|n| format!("{n}\n")).collect();
+    let output = sort(&["-n", "-S", "100b", "--batch-size", "2", "--progress"], &input);
+    assert_eq!(stdout(&output), expected);
+    assert!(stderr(&output).contains("in batches of 2"));
+}
+
+#[test]
+fn batch_size_limits_the_files_merged_at_once() {
+    let files: Vec<PathBuf> = (1..=5)
+        .map(|start| temp_file(&format!("batch-merge-{start}"), &(start..=40).step_by(5).map(|n| format!("{n}\n")).collect::<String>()))
+        .collect();
+    let mut args = vec!["-m", "-n", "--batch-size", "2", "--progress"];
+    args.extend(files.iter().map(|path| path.to_str().unwrap()));
+    let output = sort(&args, "");
+    let expected: String = (1..=40).map(|n| format!("{n}\n")).collect();
+    assert_eq!(stdout(&output), expected);
+    assert!(stderr(&output).contains("sort: merging 3 runs in batches of 2"));
+}
+
+#[test]
+fn streamed_output_matches_joined_lines() {
+    let mut lines: Vec<String> = (0..1000).map(|n| format!("line {}", (n * 31) % 1000)).collect();
+    let input = lines.join("\n");
+    lines.sort();
+    let output = sort(&[], &input);
+    assert_eq!(output.stdout, format!("{}\n", lines.join("\n")).into_bytes());
+}
+
+#[test]
+fn ignore_case_with_months_folds_the_tie_break() {
+    let output = sort(&["-f", "-M"], "feb b\nFEB A\njan z\nFeb a\n");
+    assert_eq!(stdout(&output), "jan z\nFEB A\nFeb a\nfeb b\n");
+}
+
+#[test]
+fn check_unique_reports_the_first_duplicate() {
+    let output = sort(&["-cu"], "a\nb\nb\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "sort: -:3: duplicate: b\n");
+}
+
+#[test]
+fn sort_options_supplies_defaults() {
+    let output = sort_with(&["-k2"], b"b,2\na,1\n", &[("SORT_OPTIONS", "-t,")]);
+    assert_eq!(stdout(&output), "a,1\nb,2\n");
+}
+
+#[test]
+fn sort_options_rejects_file_operands() {
+    let output = sort_with(&[], b"b\na\n", &[("SORT_OPTIONS", "-n data.txt")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "sort: SORT_OPTIONS: file operands are not allowed: 'data.txt'\n");
+}
+
+#[test]
+fn sort_options_errors_name_the_variable() {
+    let output = sort_with(&[], b"a\n", &[("SORT_OPTIONS", "--no-such-option")]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "sort: SORT_OPTIONS: unexpected argument '--no-such-option' found\n");
+}
+
+#[test]
+fn explicit_flags_override_conflicting_sort_options() {
+    let output = sort_with(&["-M"], b"mar\njan\nfeb\n", &[("SORT_OPTIONS", "-n")]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "jan\nfeb\nmar\n");
+}
+
+#[test]
+fn dry_run_writes_no_output_file() {
+    let target = env::temp_dir().join(format!("sort-test-{}-dry-run", process::id()));
+    let output = sort(&["--dry-run", "-o", target.to_str().unwrap()], "b\na\n");
+    assert!(output.status.success());
+    assert!(!target.exists());
+    assert_eq!(stderr(&output), format!("sort: dry run: would write 2 lines to {}\n", target.display()));
+}
+
+#[test]
+fn dry_run_leaves_an_in_place_file_alone() {
+    let path = temp_file("dry-run-in-place", "b\na\n");
+    sort(&["--dry-run", "-I", path.to_str().unwrap()], "");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "b\na\n");
+}
+
+#[test]
+fn month_ties_are_broken_by_the_whole_line() {
+    let input = "jan b\njan a\nfeb x\njan c\n";
+    let first = sort(&["-M"], input);
+    assert_eq!(stdout(&first), "jan a\njan b\njan c\nfeb x\n");
+    assert_eq!(stdout(&sort(&["-M"], "jan c\nfeb x\njan a\njan b\n")), stdout(&first));
+    assert_eq!(stdout(&sort(&["-M", "-s"], input)), "jan b\njan a\njan c\nfeb x\n");
+}
+
+#[test]
+fn double_dash_ends_option_parsing() {
+    let directory = env::temp_dir().join(format!("sort-test-{}-dash", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("-n.txt"), "b\na\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_sort"))
+        .current_dir(&directory)
+        .args(["--", "-n.txt"])
+        .env_remove("SORT_OPTIONS")
+        .output()
+        .expect("sort binary should run");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "a\nb\n");
+}
+
+#[test]
+fn output_separator_joins_records() {
+    let output = sort(&["--output-separator", ", "], "c\na\nb\n");
+    assert_eq!(stdout(&output), "a, b, c\n");
+}
+
+#[test]
+fn warns_about_options_that_do_nothing() {
+    let target = env::temp_dir().join(format!("sort-test-{}-check-output", process::id()));
+    let check = sort(&["-c", "-o", target.to_str().unwrap()], "a\n");
+    assert!(stderr(&check).contains("sort: warning: -o has no effect when checking order"));
+    let seed = temp_file("unused-seed", "seed\n");
+    let random = sort(&["--random-source", seed.to_str().unwrap()], "a\n");
+    assert!(stderr(&random).contains("sort: warning: --random-source has no effect without -R"));
+    let color = sort(&["--color=always"], "a\n");
+    assert!(stderr(&color).contains("sort: warning: --color has no effect without --debug"));
+}
+
+#[test]
+fn multi_character_separator_splits_fields() {
+    let output = sort(&["--field-separator-str", "||", "-k2"], "c||x\na||z||b\nb||y\n");
+    assert_eq!(stdout(&output), "c||x\nb||y\na||z||b\n");
+}
+
+#[test]
+fn currency_symbols_are_ignored_in_numeric_keys() {
+    assert_eq!(stdout(&sort(&["-n", "--currency"], "$100\n$5\n$10\n")), "$5\n$10\n$100\n");
+    let output = sort(&["-n", "--currency", "--thousands-sep", ","], "$1,000\n$999\n");
+    assert_eq!(stdout(&output), "$999\n$1,000\n");
+}
+
+#[test]
+fn random_sort_keeps_equal_keys_together() {
+    let seed = temp_file("random-group-seed", "seed\n");
+    let args = ["-R", "-k1,1", "--random-source", seed.to_str().unwrap()];
+    let output = sort(&args, "a 1\nb 1\na 2\nc 1\nb 2\na 3\n");
+    let printed = stdout(&output);
+    let mut groups: Vec<&str> = printed.lines().map(|line| &line[..1]).collect();
+    groups.dedup();
+    assert_eq!(groups.len(), 3);
+}
+
+#[test]
+fn random_sort_groups_keys_that_compare_equal() {
+    let seed = temp_file("random-equal-seed", "seed\n");
+    let numeric = sort(&["-R", "-k1n", "--random-source", seed.to_str().unwrap()], "1 a\n2 b\n1.0 c\n3 d\n01 e\n");
+    let printed = stdout(&numeric);
+    let mut values: Vec<f64> = printed.lines().map(|line| line.split(' ').next().unwrap().parse().unwrap()).collect();
+    values.dedup();
+    assert_eq!(values.len(), 3);
+    let months = sort(&["-R", "-k1M", "--random-source", seed.to_str().unwrap()], "jan a\nfeb b\nJAN c\nmar d\nJanuary e\n");
+    let printed = stdout(&months);
+    let mut names: Vec<String> = printed.lines().map(|line| line[..3].to_lowercase()).collect();
+    names.dedup();
+    assert_eq!(names.len(), 3);
+}
+
+#[test]
+fn random_unique_collapses_keys_that_compare_equal() {
+    let output = sort(&["-R", "-k1n", "-u"], "1\n1.0\n2\n01\n");
+    let mut printed: Vec<String> = stdout(&output).lines().map(String::from).collect();
+    printed.sort();
+    assert_eq!(printed, ["1", "2"]);
+}
+
+#[test]
+fn every_mode_is_stable_under_s() {
+    let cases: [(&str, &str); 7] = [
+        ("-k1,1", "b z\na y\nb x\na w\n"),
+        ("-n", "2 z\n1 y\n2 x\n1 w\n"),
+        ("-g", "2e0 z\n1 y\n2e0 x\n1 w\n"),
+        ("-M", "feb z\njan y\nfeb x\njan w\n"),
+        ("-h", "2K z\n1K y\n2K x\n1K w\n"),
+        ("-V", "v2 z\nv1 y\nv2 x\nv1 w\n"),
+        ("--radix=16", "0x2 z\n0x1 y\n0x2 x\n0x1 w\n"),
+    ];
+    for (mode, input) in cases {
+        let args = [mode, "-k1,1", "-s"];
+        let printed = stdout(&sort(&args, input));
+        let tags: Vec<&str> = printed.lines().map(|line| line.rsplit(' ').next().unwrap()).collect();
+        assert_eq!(tags, ["y", "w", "z", "x"], "{mode} should keep input order of ties");
+    }
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn compresses_output_ending_in_gz() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let target = env::temp_dir().join(format!("sort-test-{}-out.gz", process::id()));
+    let output = sort(&["-o", target.to_str().unwrap()], "b\nc\na\n");
+    assert!(output.status.success());
+    let mut contents = String::new();
+    GzDecoder::new(fs::File::open(&target).unwrap()).read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "a\nb\nc\n");
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compresses_output_ending_in_zst() {
+    let target = env::temp_dir().join(format!("sort-test-{}-out.zst", process::id()));
+    let output = sort(&["-o", target.to_str().unwrap()], "b\nc\na\n");
+    assert!(output.status.success());
+    let contents = zstd::decode_all(fs::File::open(&target).unwrap()).unwrap();
+    assert_eq!(contents, b"a\nb\nc\n");
+}
+
+#[test]
+fn check_reports_line_numbers_for_months_and_sizes() {
+    assert_eq!(stderr(&sort(&["-c", "-M"], "jan\nmar\nfeb\n")), "sort: -:3: disorder: feb\n");
+    assert_eq!(stderr(&sort(&["-c", "-h"], "1K\n1M\n2K\n5M\n")), "sort: -:3: disorder: 2K\n");
+}
+
+#[test]
+fn rejects_a_key_without_a_start_field() {
+    for value in [",3", ""] {
+        let output = sort(&["-k", value], "a\n");
+        assert_eq!(output.status.code(), Some(2));
+        assert_eq!(stderr(&output), format!("sort: missing start field number: invalid field specification '{value}'\n"));
+    }
+}
+
+#[test]
+fn debug_color_stays_off_without_a_terminal() {
+    for args in [&["--debug", "-k2,2"][..], &["--debug", "-k2,2", "--color=never"], &["--debug", "-k2,2", "--color"]] {
+        let output = sort_with(args, b"x abc y\n", &[]);
+        assert!(!output.stdout.contains(&0x1b), "{args:?} should not emit ANSI codes");
+    }
+    let output = sort_with(&["--debug", "--color=auto"], b"a\n", &[("NO_COLOR", "1")]);
+    assert!(!output.stdout.contains(&0x1b));
+}
+
+#[test]
+fn trailing_separator_ends_with_an_empty_field() {
+    let output = sort(&["-t,", "-k3"], "c,d,a\na,b,\nx,y\n");
+    assert_eq!(stdout(&output), "a,b,\nx,y\nc,d,a\n");
+}
+
+#[test]
+fn leading_separator_starts_with_an_empty_field() {
+    assert_eq!(stdout(&sort(&["-t,", "-k1,1"], "z,c\n,b\n,a\n")), ",a\n,b\nz,c\n");
+    assert_eq!(stdout(&sort(&["-t,", "-k2,2"], ",b\nx,a\n")), "x,a\n,b\n");
+}
+
+#[test]
+fn length_sort_orders_by_length_with_text_ties() {
+    assert_eq!(stdout(&sort(&["-L"], "ccc\nbb\naa\na\ndd\n")), "a\naa\nbb\ndd\nccc\n");
+    assert_eq!(stdout(&sort(&["-L", "-r"], "ccc\nbb\naa\na\n")), "ccc\nbb\naa\na\n");
+    assert_eq!(stdout(&sort(&["-L", "-u"], "cd\nab\nab\n")), "ab\ncd\n");
+}
+
+#[test]
+fn length_sort_can_count_characters() {
+    assert_eq!(stdout(&sort(&["-L"], "\u{e9}\u{e9}\nabc\n")), "abc\n\u{e9}\u{e9}\n");
+    assert_eq!(stdout(&sort(&["-L", "--length-unit", "chars"], "abc\n\u{e9}\u{e9}\n")), "\u{e9}\u{e9}\nabc\n");
+}
+
+#[test]
+fn check_splits_zero_terminated_records() {
+    let unsorted = sort_with(&["-cz"], b"a\0c\0b\0", &[]);
+    assert_eq!(unsorted.status.code(), Some(1));
+    assert_eq!(stderr(&unsorted), "sort: -:3: disorder: b\n");
+    assert_eq!(sort_with(&["-cz"], b"a\nz\0b\0", &[]).status.code(), Some(0));
+}
+
+#[test]
+fn ignore_case_unique_keeps_one_of_each_case_variant() {
+    let output = sort(&["-fu"], "apple\nApple\nb\nAPPLE\n");
+    assert_eq!(stdout(&output), "apple\nb\n");
+}